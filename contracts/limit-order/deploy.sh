@@ -14,6 +14,8 @@ ORACLE_EXTERNAL_DEX="CCYOZJCOPG34LLQQ7N24YXBM7LL62R7ONMZ3G6WZAAYPB5OYKOMJRN63"
 ORACLE_FIAT="CCSSOHTBL3LEWUCBBEB5NJFC2OKFRC74OWEIJIZLRJBGAAU4VMU5NV4W"
 SOROSWAP_ROUTER="CCMAPXWVZD4USEKDWRYS7DA4Y3D7E2SDMGBFJUCEXTC7VN6CUBGWPFUS"
 AQUA_TOKEN="GBNZILSTVQZ4R7IKQDGHYGY2QXL5QOFJYQMXPKWRRM5PAV7Y4M67AQUA"
+XLM_TOKEN="CDLZFC3SYJYDZT7K67VZ75HPJVIEUVNIXF47ZG2FB2RMQQVU2HHGCYSC"
+USDC_TOKEN="CBBHRKEP5M3NUDRISGLJKGHDHX3DA2CN2AZBQY6WLVUJ7VNLGSKBDUCM"
 ADMIN="GBASEG66SOECYUGOMNYUXZANKMJGFN6FJDRU47HCNL5UZJP72CE4M2S4"
 
 
@@ -77,9 +79,22 @@ stellar contract invoke \
     --network testnet \
     -- \
     set_oracle \
-    --admin "$ADMIN" \
+    --operator "$ADMIN" \
     --oracle "$ORACLE"
 
+# List the tokens intents can trade (order sizes in stroops: 1 to 1,000,000 units)
+for TOKEN in "$XLM_TOKEN" "$USDC_TOKEN"; do
+    stellar contract invoke \
+        --id "$CONTRACT_ID" \
+        --source-account default \
+        --network testnet \
+        -- \
+        set_token_config \
+        --operator "$ADMIN" \
+        --token "$TOKEN" \
+        --config "{\"min_order_size\":\"10000000\",\"max_order_size\":\"10000000000000\",\"oracle_asset\":{\"Stellar\":\"$TOKEN\"},\"oracle\":null}"
+done
+
 echo "✅ Contract initialized!"
 echo ""
 
@@ -1,16 +1,47 @@
-use soroban_sdk::{contract, contractimpl, token, Address, Env};
+use soroban_sdk::{contract, contractimpl, token, Address, BytesN, Env, Map, Symbol, Vec};
+use stellar_access::access_control::{self as access_control, AccessControl};
+use stellar_macros::{default_impl, only_admin, only_role};
+use stellar_contract_utils::pausable::{self as pausable, Pausable};
 
 use crate::error::Error;
+use crate::events;
+use crate::math;
 use crate::storage;
-use crate::types::{Balance, Intent, IntentStatus, PRICE_SCALE};
+use crate::types::{
+    Balance, BatchMode, FeeConfig, IncentiveCurve, Intent, IntentGroup, IntentParams,
+    IntentStatus, Ladder, LadderDistribution, OrderBookEntry, PauseScope, PriceSource,
+    TokenConfig, TriggerDirection, TtlConfig, BPS_DENOMINATOR, MAX_BATCH_SIZE, MAX_FEE_BPS,
+    MAX_GROUP_SIZE, MAX_PAGE_SIZE, MAX_TRANCHES, MIN_TTL_EXTEND_TO, PRICE_SCALE, SCHEMA_VERSION,
+};
 
 #[contract]
 pub struct LimitOrderContract;
 
+/// Storage updates of a fill, computed by prepare_fill before any tokens move
+struct FillSettlement {
+    /// The intent with the fill recorded (executor set when applied)
+    intent: Intent,
+    fill_amount: i128,
+    /// Buy amount the creator received, net of the buy-side fee
+    buy_amount: i128,
+    /// Executor's incentive share, at its current value
+    incentive: i128,
+    /// Creator's sell token balance after the fill
+    creator_balance: Balance,
+    /// Group closed by the fill, and the shared lock it releases
+    group: Option<(IntentGroup, i128)>,
+    /// (token, fee kept, accrued fees after the fill) for the sell and buy tokens
+    fees: [(Address, i128, i128); 2],
+}
+
 #[contractimpl]
 impl LimitOrderContract {
     /// Initialize the contract with an admin, Soroswap router, and Reflector oracle
-    /// @param admin: Admin address for emergency functions
+    /// The admin starts with every role and can grant them to other accounts:
+    /// - config_manager: router, oracle, routing and price check settings
+    /// - guardian: emergency cancellation of intents
+    /// - fee_manager: protocol fee and treasury
+    /// @param admin: Admin address (manages roles, transferable in two steps)
     /// @param router: Soroswap router contract address for DEX swaps
     /// @param oracle: Reflector oracle contract address for price verification
     ///
@@ -18,13 +49,17 @@ impl LimitOrderContract {
     /// - Router: CCMAPXWVZD4USEKDWRYS7DA4Y3D7E2SDMGBFJUCEXTC7VN6CUBGWPFUS
     /// - Oracle: CAVLP5DH2GJPZMVO7IJY4CVOD5MWEFTJFVPD2YY2FQXOQHRGHK4D6HLP
     pub fn __constructor(e: Env, admin: Address, router: Address, oracle: Address) {
-        storage::set_admin(&e, &admin);
+        access_control::set_admin(&e, &admin);
+        for role in ["config_manager", "guardian", "fee_manager"] {
+            access_control::grant_role_no_auth(&e, &admin, &admin, &Symbol::new(&e, role));
+        }
         storage::set_router(&e, &router);
         storage::set_oracle(&e, &oracle);
+        storage::set_schema_version(&e, SCHEMA_VERSION);
     }
 
     /// Deposit tokens into the vault
-    /// @param token: Token contract address
+    /// @param token: Token contract address (must be listed)
     /// @param amount: Amount to deposit
     /// @param from: User depositing the tokens
     pub fn deposit(e: Env, token: Address, amount: i128, from: Address) -> Result<(), Error> {
@@ -33,20 +68,32 @@ impl LimitOrderContract {
         }
 
         from.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::Deposits)?;
+        if !storage::is_supported_token(&e, &token) {
+            return Err(Error::InvalidToken);
+        }
 
         // Transfer tokens from user to contract
         let client = token::Client::new(&e, &token);
-        client.transfer(&from, &e.current_contract_address(), &amount);
+        client.transfer(&from, e.current_contract_address(), &amount);
 
         // Update user balance
         let mut balance = storage::get_balance(&e, &from, &token);
-        balance.available += amount;
+        balance.available = math::add(balance.available, amount)?;
         storage::set_balance(&e, &from, &token, &balance);
 
+        events::Deposit {
+            user: from,
+            token,
+            amount,
+        }
+        .publish(&e);
+
         Ok(())
     }
 
     /// Withdraw available tokens from the vault
+    /// Withdrawals don't depend on the token allowlist, so funds can always leave the vault
     /// @param token: Token contract address
     /// @param amount: Amount to withdraw
     /// @param to: Recipient address
@@ -64,13 +111,20 @@ impl LimitOrderContract {
         }
 
         // Update balance
-        balance.available -= amount;
+        balance.available = math::sub(balance.available, amount)?;
         storage::set_balance(&e, &to, &token, &balance);
 
         // Transfer tokens to user
         let client = token::Client::new(&e, &token);
         client.transfer(&e.current_contract_address(), &to, &amount);
 
+        events::Withdraw {
+            user: to,
+            token,
+            amount,
+        }
+        .publish(&e);
+
         Ok(())
     }
 
@@ -79,9 +133,13 @@ impl LimitOrderContract {
     /// @param sell_amount: Amount to sell
     /// @param buy_token: Token to buy
     /// @param min_buy_amount: Minimum amount to receive
-    /// @param target_price: Target price (scaled by PRICE_SCALE)
+    /// @param target_price: Target price in whole buy tokens per whole sell token (scaled by PRICE_SCALE)
     /// @param incentive: Reward for executor
     /// @param expiry: Expiration timestamp
+    /// @param price_source: Oracle price used for trigger checks and execution
+    /// @param trigger_direction: Above for limit/take-profit, Below for stop-loss,
+    ///                           TrailingStop(bps) to trail the high-water mark
+    ///                           (target_price is then the initial high-water mark)
     pub fn create_intent(
         e: Env,
         creator: Address,
@@ -92,90 +150,421 @@ impl LimitOrderContract {
         target_price: i128,
         incentive: i128,
         expiry: u64,
+        price_source: PriceSource,
+        trigger_direction: TriggerDirection,
     ) -> Result<u64, Error> {
         creator.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::NewIntents)?;
 
-        // Validation
-        if sell_amount <= 0 || min_buy_amount <= 0 {
+        let params = IntentParams {
+            sell_token,
+            sell_amount,
+            buy_token,
+            min_buy_amount,
+            target_price,
+            incentive,
+            expiry,
+            price_source,
+            trigger_direction,
+        };
+
+        Self::place_intent(&e, &creator, params)
+    }
+
+    /// Create several intents in one call, e.g. a ladder of limit orders
+    /// @param creator: Address of the intents' creator
+    /// @param intents: Parameters of each intent (1 to MAX_BATCH_SIZE), as in create_intent
+    /// @param mode: AllOrNothing reverts on the first invalid intent; BestEffort
+    ///              skips invalid intents
+    /// @returns: Per-intent result, in order: the new intent ID or why it was not created
+    pub fn create_intents(
+        e: Env,
+        creator: Address,
+        intents: Vec<IntentParams>,
+        mode: BatchMode,
+    ) -> Result<Vec<Result<u64, Error>>, Error> {
+        creator.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::NewIntents)?;
+        Self::ensure_batch_size(intents.len())?;
+
+        let mut results = Vec::new(&e);
+        for params in intents.iter() {
+            let result = Self::place_intent(&e, &creator, params);
+            results.push_back(Self::batch_result(&mode, result)?);
+        }
+        Ok(results)
+    }
+
+    /// Create an intent whose executor incentive ramps up over time (Dutch auction)
+    /// The incentive starts at min_incentive at creation and rises to
+    /// params.incentive at expiry, linearly or in equal steps. Executors are paid
+    /// the value at execution time; the unused part is refunded to the creator's
+    /// available balance. params.incentive is locked upfront.
+    ///
+    /// @param creator: Address of the intent creator
+    /// @param params: Intent parameters; incentive is the maximum incentive
+    /// @param min_incentive: Incentive at creation (0 to params.incentive)
+    /// @param curve: Linear or Stepwise(steps) ramp from min to max incentive
+    pub fn create_auction_intent(
+        e: Env,
+        creator: Address,
+        params: IntentParams,
+        min_incentive: i128,
+        curve: IncentiveCurve,
+    ) -> Result<u64, Error> {
+        creator.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::NewIntents)?;
+
+        Self::validate_intent(&e, &params)?;
+        if min_incentive < 0 || min_incentive > params.incentive {
             return Err(Error::InvalidAmount);
         }
-        if target_price <= 0 {
-            return Err(Error::InvalidPrice);
+        if curve == IncentiveCurve::Stepwise(0) {
+            return Err(Error::InvalidConfig);
+        }
+
+        let locked = math::add(params.sell_amount, params.incentive)?;
+        Self::lock_funds(&e, &creator, &params.sell_token, locked)?;
+
+        let mut intent = Self::new_intent(&e, &creator, params);
+        intent.min_incentive = min_incentive;
+        intent.incentive_curve = curve;
+        Ok(Self::store_intent(&e, &intent))
+    }
+
+    /// Create a recurring (DCA) intent selling one tranche every interval
+    /// e.g. sell 100 XLM for USDC every day for 30 days
+    ///
+    /// Each tranche can be executed by anyone once its window opens, subject to
+    /// the same target, min buy and oracle checks as a regular intent. The first
+    /// window opens at creation; each following one opens `interval` seconds
+    /// after the previous one opened, whenever that tranche was executed. All
+    /// tranches are locked upfront.
+    ///
+    /// @param creator: Address of the intent creator
+    /// @param params: Intent parameters; sell_amount, min_buy_amount and incentive
+    ///                are per tranche. The sell token's min_order_size applies to
+    ///                each tranche and its max_order_size to the total
+    /// @param interval: Minimum delay between two tranches, in seconds
+    /// @param tranches: Number of tranches (1 to MAX_TRANCHES); params.expiry must
+    ///                  not come before the last tranche's window opens
+    pub fn create_recurring_intent(
+        e: Env,
+        creator: Address,
+        params: IntentParams,
+        interval: u64,
+        tranches: u32,
+    ) -> Result<u64, Error> {
+        creator.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::NewIntents)?;
+
+        Self::validate_intent(&e, &params)?;
+        if interval == 0 || tranches == 0 || tranches > MAX_TRANCHES {
+            return Err(Error::InvalidConfig);
+        }
+
+        // Every tranche's window must open before the intent expires
+        let last_window = (tranches as u64 - 1)
+            .checked_mul(interval)
+            .and_then(|delay| e.ledger().timestamp().checked_add(delay))
+            .ok_or(Error::MathOverflow)?;
+        if params.expiry < last_window {
+            return Err(Error::InvalidConfig);
         }
-        if incentive < 0 || incentive > sell_amount {
+
+        // The intent holds the totals over all tranches
+        let count = tranches as i128;
+        let amount_per_interval = params.sell_amount;
+        let params = IntentParams {
+            sell_amount: math::mul(params.sell_amount, count)?,
+            min_buy_amount: math::mul(params.min_buy_amount, count)?,
+            incentive: math::mul(params.incentive, count)?,
+            ..params
+        };
+        let sell_config =
+            storage::get_token_config(&e, &params.sell_token).ok_or(Error::InvalidToken)?;
+        if params.sell_amount > sell_config.max_order_size {
             return Err(Error::InvalidAmount);
         }
 
-        let current_time = e.ledger().timestamp();
-        if expiry <= current_time {
-            return Err(Error::IntentExpired);
+        let locked = math::add(params.sell_amount, params.incentive)?;
+        Self::lock_funds(&e, &creator, &params.sell_token, locked)?;
+
+        let mut intent = Self::new_intent(&e, &creator, params);
+        intent.amount_per_interval = amount_per_interval;
+        intent.interval = interval;
+        intent.tranches_remaining = tranches;
+        Ok(Self::store_intent(&e, &intent))
+    }
+
+    /// Create a one-cancels-other group of intents selling the same token
+    /// e.g. a take-profit and a stop-loss on the same position
+    ///
+    /// The group is backed by a single lock of the largest sell_amount + incentive
+    /// among its members. The first fill of any member cancels its siblings and
+    /// releases the rest of the shared lock.
+    ///
+    /// @param creator: Address of the group creator
+    /// @param intents: Parameters of the member intents (2 to MAX_GROUP_SIZE)
+    /// @returns: Group ID (member intent IDs are listed by get_intent_group)
+    pub fn create_intent_group(
+        e: Env,
+        creator: Address,
+        intents: Vec<IntentParams>,
+    ) -> Result<u64, Error> {
+        creator.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::NewIntents)?;
+
+        if intents.len() < 2 || intents.len() > MAX_GROUP_SIZE {
+            return Err(Error::InvalidGroup);
         }
 
-        // Check balance and lock funds
-        let total_required = sell_amount + incentive;
-        let mut balance = storage::get_balance(&e, &creator, &sell_token);
+        // Every member must sell the same token to share one lock
+        let sell_token = intents.get_unchecked(0).sell_token;
+        let mut locked = 0;
+        for params in intents.iter() {
+            if params.sell_token != sell_token {
+                return Err(Error::InvalidToken);
+            }
+            Self::validate_intent(&e, &params)?;
+            locked = locked.max(math::add(params.sell_amount, params.incentive)?);
+        }
 
-        if balance.available < total_required {
-            return Err(Error::InsufficientBalance);
+        Self::lock_funds(&e, &creator, &sell_token, locked)?;
+
+        let group_id = storage::get_next_group_id(&e);
+        let mut intent_ids = Vec::new(&e);
+        for params in intents.iter() {
+            let mut intent = Self::new_intent(&e, &creator, params);
+            intent.group_id = Some(group_id);
+            intent_ids.push_back(Self::store_intent(&e, &intent));
         }
 
-        // Lock the funds
-        balance.available -= total_required;
-        balance.locked += total_required;
-        storage::set_balance(&e, &creator, &sell_token, &balance);
+        storage::set_intent_group(
+            &e,
+            &IntentGroup {
+                id: group_id,
+                creator: creator.clone(),
+                sell_token,
+                intent_ids: intent_ids.clone(),
+                locked,
+                filled_intent: None,
+            },
+        );
 
-        // Create intent
-        let intent_id = storage::get_next_intent_id(&e);
-        let intent = Intent {
-            id: intent_id,
-            creator: creator.clone(),
-            sell_token,
-            sell_amount,
-            buy_token,
-            min_buy_amount,
-            target_price,
-            incentive,
-            expiry,
-            status: IntentStatus::Active,
-            executor: None,
-            actual_buy_amount: None,
-        };
+        events::IntentGroupCreated {
+            group_id,
+            creator,
+            intent_ids,
+            locked,
+        }
+        .publish(&e);
 
-        storage::set_intent(&e, intent_id, &intent);
-        storage::add_user_intent(&e, &creator, intent_id);
+        Ok(group_id)
+    }
+
+    /// Create a price ladder: sell `total_amount` across `steps` limit orders
+    /// evenly spaced from `low_price` to `high_price`
+    /// e.g. sell 1,000 XLM between 0.10 and 0.16 USDC in 4 levels
+    ///
+    /// Each level is a regular take-profit intent (Spot price source) that fills
+    /// independently; its min_buy_amount is its size at its price. The incentive
+    /// is split across levels like the amount, the highest level receiving any
+    /// rounding remainder. Fails unless every level is a valid intent.
+    ///
+    /// @param creator: Address of the ladder creator
+    /// @param sell_token: Token to sell
+    /// @param buy_token: Token to buy
+    /// @param total_amount: Amount of sell_token sold over all levels
+    /// @param low_price: Target price of the lowest level (scaled by PRICE_SCALE)
+    /// @param high_price: Target price of the highest level (scaled by PRICE_SCALE)
+    /// @param steps: Number of levels (2 to MAX_BATCH_SIZE)
+    /// @param distribution: Linear or Geometric(ratio_bps) sizes, from low to high price
+    /// @param incentive: Total reward for executors, in sell_token
+    /// @param expiry: Expiration timestamp of every level
+    /// @returns: Ladder ID (member intent IDs are listed by get_ladder)
+    pub fn create_ladder(
+        e: Env,
+        creator: Address,
+        sell_token: Address,
+        buy_token: Address,
+        total_amount: i128,
+        low_price: i128,
+        high_price: i128,
+        steps: u32,
+        distribution: LadderDistribution,
+        incentive: i128,
+        expiry: u64,
+    ) -> Result<u64, Error> {
+        creator.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::NewIntents)?;
+
+        if steps < 2 {
+            return Err(Error::InvalidConfig);
+        }
+        Self::ensure_batch_size(steps)?;
+        if low_price <= 0 || low_price >= high_price {
+            return Err(Error::InvalidPrice);
+        }
+
+        let weights = Self::ladder_weights(&e, steps, &distribution)?;
+        let mut total_weight = 0;
+        for weight in weights.iter() {
+            total_weight = math::add(total_weight, weight)?;
+        }
+
+        let mut intent_ids = Vec::new(&e);
+        let (mut allocated, mut allocated_incentive) = (0, 0);
+        for (level, weight) in weights.iter().enumerate() {
+            let (sell_amount, level_incentive) = if level as u32 == steps - 1 {
+                (
+                    math::sub(total_amount, allocated)?,
+                    math::sub(incentive, allocated_incentive)?,
+                )
+            } else {
+                (
+                    math::mul_div_floor(total_amount, weight, total_weight)?,
+                    math::mul_div_floor(incentive, weight, total_weight)?,
+                )
+            };
+            allocated = math::add(allocated, sell_amount)?;
+            allocated_incentive = math::add(allocated_incentive, level_incentive)?;
+
+            let target_price = math::add(
+                low_price,
+                math::mul_div_floor(high_price - low_price, level as i128, steps as i128 - 1)?,
+            )?;
+            let params = IntentParams {
+                sell_token: sell_token.clone(),
+                sell_amount,
+                buy_token: buy_token.clone(),
+                min_buy_amount: Self::buy_amount_at(
+                    &e,
+                    &sell_token,
+                    &buy_token,
+                    sell_amount,
+                    target_price,
+                )?,
+                target_price,
+                incentive: level_incentive,
+                expiry,
+                price_source: PriceSource::Spot,
+                trigger_direction: TriggerDirection::Above,
+            };
+            intent_ids.push_back(Self::place_intent(&e, &creator, params)?);
+        }
+
+        let ladder_id = storage::get_next_ladder_id(&e);
+        storage::set_ladder(
+            &e,
+            &Ladder {
+                id: ladder_id,
+                creator: creator.clone(),
+                sell_token,
+                buy_token,
+                intent_ids: intent_ids.clone(),
+            },
+        );
+
+        events::LadderCreated {
+            ladder_id,
+            creator,
+            intent_ids,
+            total_amount,
+        }
+        .publish(&e);
 
-        Ok(intent_id)
+        Ok(ladder_id)
     }
 
-    /// Execute a limit order intent
+    /// Execute a limit order intent, fully or partially
     /// This is called by community executors when price conditions are met
     ///
     /// FLOW:
     /// 1. Executor monitors SDEX/AMM prices
     /// 2. When target price is reached, executor calls this function
-    /// 3. Contract verifies price and transfers tokens
-    /// 4. Executor gets sell_amount + incentive to execute trade on DEX
+    /// 3. Contract verifies price against the oracle and transfers tokens
+    /// 4. Executor gets fill_amount + pro-rata incentive to execute trade on DEX
     /// 5. Executor must provide buy_tokens back to creator
     ///
+    /// The intent stays PartiallyFilled until its whole sell_amount is filled.
+    ///
     /// @param intent_id: ID of the intent to execute
     /// @param executor: Address of the executor
-    /// @param buy_amount: Actual amount of buy_token obtained from the swap
+    /// @param fill_amount: Amount of sell_token to fill (at most the remaining amount)
+    /// @param buy_amount: Actual amount of buy_token obtained for fill_amount
     pub fn execute_intent(
         e: Env,
         intent_id: u64,
         executor: Address,
+        fill_amount: i128,
         buy_amount: i128,
     ) -> Result<(), Error> {
         executor.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::Executions)?;
+
+        Self::fill_intent(&e, intent_id, &executor, fill_amount, buy_amount, None)
+    }
+
+    /// Execute several intents in one call, each at the current oracle price
+    /// Each fill delivers the buy amount quoted by check_intent_executable for it,
+    /// and is verified like execute_intent. A failed token transfer reverts the
+    /// whole batch, whatever the mode; any other failing fill moves no tokens and
+    /// writes nothing.
+    /// @param executor: Address of the executor
+    /// @param fills: (intent ID, fill_amount) pairs (1 to MAX_BATCH_SIZE)
+    /// @param mode: AllOrNothing reverts on the first failing fill; BestEffort
+    ///              skips failing fills
+    /// @returns: Per-fill result, in order: the buy amount delivered or why it failed
+    pub fn execute_intents(
+        e: Env,
+        executor: Address,
+        fills: Vec<(u64, i128)>,
+        mode: BatchMode,
+    ) -> Result<Vec<Result<i128, Error>>, Error> {
+        executor.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::Executions)?;
+        Self::ensure_batch_size(fills.len())?;
+
+        let mut results = Vec::new(&e);
+        for (intent_id, fill_amount) in fills.iter() {
+            let result = Self::fill_intent_at_oracle_price(&e, intent_id, &executor, fill_amount);
+            results.push_back(Self::batch_result(&mode, result)?);
+        }
+        Ok(results)
+    }
+
+    /// Execute a limit order intent atomically through the Soroswap router
+    /// Anyone can call this when the price condition is met
+    ///
+    /// FLOW:
+    /// 1. Contract swaps the remaining sell amount on Soroswap (min_buy_amount as floor)
+    /// 2. Contract verifies the swap output against target and oracle price
+    /// 3. Swap output is paid to the creator, incentive to the caller
+    ///
+    /// The caller needs no buy_token inventory and the creator does not rely
+    /// on an executor-reported buy_amount.
+    ///
+    /// @param intent_id: ID of the intent to execute
+    /// @param executor: Address of the caller (receives the incentive)
+    /// @param route: Optional swap path from sell_token to buy_token;
+    ///               defaults to the best direct or two-hop path
+    /// @returns: Amount of buy_token delivered to the creator (net of protocol fee)
+    pub fn execute_intent_via_router(
+        e: Env,
+        intent_id: u64,
+        executor: Address,
+        route: Option<Vec<Address>>,
+    ) -> Result<i128, Error> {
+        executor.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::Executions)?;
 
         // Get intent
         let mut intent = storage::get_intent(&e, intent_id)
             .ok_or(Error::IntentNotFound)?;
 
         // Check intent status
-        if intent.status != IntentStatus::Active {
-            return Err(Error::IntentAlreadyExecuted);
-        }
+        Self::ensure_open(&intent)?;
 
         // Check expiry
         let current_time = e.ledger().timestamp();
@@ -183,54 +572,82 @@ impl LimitOrderContract {
             return Err(Error::IntentExpired);
         }
 
-        // Verify minimum buy amount
-        if buy_amount < intent.min_buy_amount {
-            return Err(Error::MinBuyAmountNotMet);
-        }
+        let router = storage::get_router(&e).ok_or(Error::Unauthorized)?;
 
-        // Verify price condition
-        // actual_price = buy_amount / sell_amount (scaled by PRICE_SCALE)
-        let actual_price = (buy_amount * PRICE_SCALE) / intent.sell_amount;
-        if actual_price < intent.target_price {
-            return Err(Error::PriceConditionNotMet);
+        // Trailing stops trigger relative to the latest high-water mark
+        Self::refresh_high_water_mark(&e, &mut intent)?;
+
+        // Fill whatever is left of the intent (the next tranche for recurring intents)
+        let fill_amount = intent.fillable_amount(current_time);
+        if fill_amount == 0 {
+            return Err(Error::TrancheNotReady);
         }
+        let min_buy_amount = intent.min_buy_for_fill(fill_amount)?;
+
+        // A sell-side protocol fee is kept before swapping
+        let fee_config = storage::get_fee_config(&e, &intent.sell_token, &intent.buy_token);
+        let sell_fee = fee_config.sell_fee(fill_amount)?;
+        let swap_amount = math::sub(fill_amount, sell_fee)?;
+
+        let path = match route {
+            Some(path) => {
+                if !crate::soroswap::is_valid_path(&path, &intent.sell_token, &intent.buy_token) {
+                    return Err(Error::InvalidPath);
+                }
+                path
+            }
+            None => {
+                let candidates = crate::soroswap::build_candidate_paths(
+                    &e,
+                    &intent.sell_token,
+                    &intent.buy_token,
+                    &storage::get_hop_tokens(&e),
+                );
+                let (best_path, _expected_output) =
+                    crate::soroswap::find_best_path(&e, &router, swap_amount, candidates)
+                        .ok_or(Error::InvalidPath)?;
+                best_path
+            }
+        };
 
-        // Execute the trade flow:
-        // 1. Transfer sell tokens from vault to executor (who will swap on DEX/AMM)
-        let sell_client = token::Client::new(&e, &intent.sell_token);
-        sell_client.transfer(
+        // Swap the locked sell tokens; the router enforces min_buy_amount
+        // The output is what the contract actually received, not what the router reports
+        let buy_client = token::Client::new(&e, &intent.buy_token);
+        let balance_before = buy_client.balance(&e.current_contract_address());
+        crate::soroswap::execute_swap(
+            &e,
+            &router,
+            swap_amount,
+            min_buy_amount,
+            path,
             &e.current_contract_address(),
-            &executor,
-            &intent.sell_amount,
+            current_time,
         );
+        let balance_after = buy_client.balance(&e.current_contract_address());
+        let buy_amount = math::sub(balance_after, balance_before)?;
 
-        // 2. Executor must have already obtained buy_tokens from DEX and transfers to creator
-        let buy_client = token::Client::new(&e, &intent.buy_token);
-        buy_client.transfer(&executor, &intent.creator, &buy_amount);
+        // A buy-side protocol fee is kept from the swap output
+        let buy_fee = fee_config.buy_fee(buy_amount)?;
+        let creator_amount = math::sub(buy_amount, buy_fee)?;
 
-        // 3. Transfer incentive reward to executor
-        sell_client.transfer(
-            &e.current_contract_address(),
-            &executor,
-            &intent.incentive,
-        );
+        // Verify price condition on the realized swap output
+        let actual_price = Self::execution_price(&e, &intent, swap_amount, buy_amount)?;
+        Self::verify_fill(&e, &intent, fill_amount, creator_amount, actual_price)?;
+        let settlement =
+            Self::prepare_fill(&e, &intent, fill_amount, creator_amount, sell_fee, buy_fee)?;
 
-        // Update creator's balance (unlock the locked funds)
-        let mut creator_balance = storage::get_balance(&e, &intent.creator, &intent.sell_token);
-        creator_balance.locked -= intent.sell_amount + intent.incentive;
-        storage::set_balance(&e, &intent.creator, &intent.sell_token, &creator_balance);
+        // Pay swap output to the creator
+        buy_client.transfer(&e.current_contract_address(), &intent.creator, &creator_amount);
 
-        // Update intent status
-        intent.status = IntentStatus::Executed;
-        intent.executor = Some(executor);
-        intent.actual_buy_amount = Some(buy_amount);
-        storage::set_intent(&e, intent_id, &intent);
+        // Pay incentive share to the caller and record the fill
+        Self::apply_fill(&e, &executor, settlement);
 
-        Ok(())
+        Ok(creator_amount)
     }
 
     /// Get price quote from Soroswap DEX
     /// This queries the Soroswap router to get the expected output amount
+    /// along the best of the direct path and two-hop paths via the hop tokens
     ///
     /// @param sell_token: Token being sold
     /// @param buy_token: Token being bought
@@ -244,16 +661,56 @@ impl LimitOrderContract {
     ) -> Result<i128, Error> {
         let router = storage::get_router(&e).ok_or(Error::Unauthorized)?;
 
-        let (_meets_price, expected_output) = crate::soroswap::check_swap_price(
+        let candidates = crate::soroswap::build_candidate_paths(
             &e,
-            &router,
             &sell_token,
             &buy_token,
-            sell_amount,
-            0, // No minimum for price check
+            &storage::get_hop_tokens(&e),
+        );
+
+        // The best path's output is the expected output
+        let best = crate::soroswap::find_best_path(&e, &router, sell_amount, candidates);
+        Ok(best.map_or(0, |(_path, expected_output)| expected_output))
+    }
+
+    /// Find the best Soroswap route for a swap
+    /// Candidates are the direct path and two-hop paths via the hop tokens
+    ///
+    /// @param sell_token: Token being sold
+    /// @param buy_token: Token being bought
+    /// @param sell_amount: Amount of sell token
+    /// @returns: (path, expected buy amount)
+    pub fn get_best_route(
+        e: Env,
+        sell_token: Address,
+        buy_token: Address,
+        sell_amount: i128,
+    ) -> Result<(Vec<Address>, i128), Error> {
+        let router = storage::get_router(&e).ok_or(Error::Unauthorized)?;
+
+        let candidates = crate::soroswap::build_candidate_paths(
+            &e,
+            &sell_token,
+            &buy_token,
+            &storage::get_hop_tokens(&e),
         );
+        crate::soroswap::find_best_path(&e, &router, sell_amount, candidates)
+            .ok_or(Error::InvalidPath)
+    }
 
-        Ok(expected_output)
+    /// Get the intermediate tokens used for multi-hop routing
+    pub fn get_hop_tokens(e: Env) -> Vec<Address> {
+        storage::get_hop_tokens(&e)
+    }
+
+    /// Update the intermediate tokens used for multi-hop routing (config manager only)
+    /// e.g. [XLM, USDC] lets EURC→AQUA route as EURC→XLM→AQUA or EURC→USDC→AQUA
+    #[only_role(operator, "config_manager")]
+    pub fn set_hop_tokens(e: Env, operator: Address, tokens: Vec<Address>) -> Result<(), Error> {
+        storage::set_hop_tokens(&e, &tokens);
+
+        events::HopTokensUpdated { tokens }.publish(&e);
+        Ok(())
     }
 
     /// Get the configured Soroswap router address
@@ -261,16 +718,12 @@ impl LimitOrderContract {
         storage::get_router(&e)
     }
 
-    /// Update the Soroswap router address (admin only)
-    pub fn set_router(e: Env, admin: Address, router: Address) -> Result<(), Error> {
-        admin.require_auth();
-
-        let stored_admin = storage::get_admin(&e).ok_or(Error::Unauthorized)?;
-        if admin != stored_admin {
-            return Err(Error::Unauthorized);
-        }
-
+    /// Update the Soroswap router address (config manager only)
+    #[only_role(operator, "config_manager")]
+    pub fn set_router(e: Env, operator: Address, router: Address) -> Result<(), Error> {
         storage::set_router(&e, &router);
+
+        events::RouterUpdated { router }.publish(&e);
         Ok(())
     }
 
@@ -279,150 +732,862 @@ impl LimitOrderContract {
         storage::get_oracle(&e)
     }
 
-    /// Update the Reflector oracle address (admin only)
-    pub fn set_oracle(e: Env, admin: Address, oracle: Address) -> Result<(), Error> {
-        admin.require_auth();
-
-        let stored_admin = storage::get_admin(&e).ok_or(Error::Unauthorized)?;
-        if admin != stored_admin {
-            return Err(Error::Unauthorized);
-        }
-
+    /// Update the Reflector oracle address (config manager only)
+    #[only_role(operator, "config_manager")]
+    pub fn set_oracle(e: Env, operator: Address, oracle: Address) -> Result<(), Error> {
         storage::set_oracle(&e, &oracle);
+
+        events::OracleUpdated { oracle }.publish(&e);
         Ok(())
     }
 
-    /// Helper function for executors to check if an intent is executable
-    /// @param intent_id: ID of the intent to check
-    /// @returns: (is_executable, current_market_buy_amount)
-    pub fn check_intent_executable(
-        e: Env,
-        intent_id: u64,
-    ) -> Result<(bool, i128), Error> {
-        let intent = storage::get_intent(&e, intent_id)
-            .ok_or(Error::IntentNotFound)?;
+    /// Get the keeper bounty for sweeping expired intents (basis points of the unpaid incentive)
+    pub fn get_keeper_bounty(e: Env) -> u32 {
+        storage::get_keeper_bounty_bps(&e)
+    }
 
-        // Check if intent is active
-        if intent.status != IntentStatus::Active {
-            return Ok((false, 0));
+    /// Update the keeper bounty for sweeping expired intents (config manager only)
+    /// @param bounty_bps: Share of each swept intent's unpaid incentive, in basis points
+    #[only_role(operator, "config_manager")]
+    pub fn set_keeper_bounty(e: Env, operator: Address, bounty_bps: u32) -> Result<(), Error> {
+        if bounty_bps as i128 > BPS_DENOMINATOR {
+            return Err(Error::InvalidConfig);
         }
 
-        // Check expiry
-        let current_time = e.ledger().timestamp();
-        if current_time > intent.expiry {
-            return Ok((false, 0));
-        }
+        storage::set_keeper_bounty_bps(&e, bounty_bps);
 
-        // Get oracle address
-        let oracle = storage::get_oracle(&e).ok_or(Error::Unauthorized)?;
+        events::KeeperBountyUpdated { bounty_bps }.publish(&e);
+        Ok(())
+    }
 
-        // Convert token addresses to Oracle Asset types
-        let sell_asset = crate::oracle::stellar_asset(intent.sell_token.clone());
-        let buy_asset = crate::oracle::stellar_asset(intent.buy_token.clone());
+    /// Get the maximum accepted oracle price age (seconds)
+    pub fn get_max_price_age(e: Env) -> u64 {
+        storage::get_max_price_age(&e)
+    }
 
-        // Check if price condition is met using Oracle
-        let (is_executable, current_price) = crate::oracle::check_price_trigger(
-            &e,
-            &oracle,
-            &sell_asset,
-            &buy_asset,
-            intent.target_price,
-            false, // Use last price for real-time execution
-        );
+    /// Update the maximum accepted oracle price age (config manager only)
+    /// Must be at least the oracle resolution, otherwise every price would be stale
+    /// @param max_age: Maximum price age in seconds
+    #[only_role(operator, "config_manager")]
+    pub fn set_max_price_age(e: Env, operator: Address, max_age: u64) -> Result<(), Error> {
+        let oracle = storage::get_oracle(&e).ok_or(Error::Unauthorized)?;
+        let resolution = crate::oracle::ReflectorClient::new(&e, &oracle).resolution();
+        if max_age < resolution as u64 {
+            return Err(Error::InvalidConfig);
+        }
 
-        // Calculate estimated buy amount based on current price
-        // price_ratio = (sell_price / buy_price) * scale
-        // estimated_buy = (sell_amount * current_price) / scale
-        let decimals = crate::oracle::ReflectorClient::new(&e, &oracle).decimals();
-        let scale = 10_i128.pow(decimals);
-        let estimated_buy_amount = (intent.sell_amount * current_price) / scale;
+        storage::set_max_price_age(&e, max_age);
 
-        Ok((is_executable, estimated_buy_amount))
+        events::MaxPriceAgeUpdated { max_age }.publish(&e);
+        Ok(())
     }
 
-    /// Cancel an active intent
-    /// Only the creator can cancel their own intent
-    /// @param intent_id: ID of the intent to cancel
-    /// @param creator: Address of the intent creator
-    pub fn cancel_intent(e: Env, intent_id: u64, creator: Address) -> Result<(), Error> {
-        creator.require_auth();
-
-        // Get intent
-        let mut intent = storage::get_intent(&e, intent_id)
-            .ok_or(Error::IntentNotFound)?;
-
-        // Verify creator
-        if intent.creator != creator {
-            return Err(Error::OnlyCreatorCanCancel);
-        }
+    /// Get the maximum allowed deviation between executor and oracle price (basis points)
+    pub fn get_max_deviation(e: Env) -> u32 {
+        storage::get_max_deviation_bps(&e)
+    }
 
-        // Check status
-        if intent.status != IntentStatus::Active {
-            return Err(Error::IntentAlreadyExecuted);
+    /// Update the maximum allowed deviation between executor and oracle price
+    /// (config manager only)
+    /// @param max_deviation_bps: Maximum deviation in basis points (10000 = 100%)
+    #[only_role(operator, "config_manager")]
+    pub fn set_max_deviation(e: Env, operator: Address, max_deviation_bps: u32) -> Result<(), Error> {
+        if max_deviation_bps as i128 > BPS_DENOMINATOR {
+            return Err(Error::InvalidConfig);
         }
 
-        // Unlock funds
-        let mut balance = storage::get_balance(&e, &creator, &intent.sell_token);
-        let total_locked = intent.sell_amount + intent.incentive;
-        balance.locked -= total_locked;
-        balance.available += total_locked;
-        storage::set_balance(&e, &creator, &intent.sell_token, &balance);
-
-        // Update intent status
-        intent.status = IntentStatus::Cancelled;
-        storage::set_intent(&e, intent_id, &intent);
+        storage::set_max_deviation_bps(&e, max_deviation_bps);
 
+        events::MaxDeviationUpdated { max_deviation_bps }.publish(&e);
         Ok(())
     }
 
-    /// Get intent details
-    pub fn get_intent(e: Env, intent_id: u64) -> Option<Intent> {
-        storage::get_intent(&e, intent_id)
+    /// Get the TTL policy of intents, balances and the contract instance
+    pub fn get_ttl_config(e: Env) -> TtlConfig {
+        storage::get_ttl_config(&e)
     }
 
-    /// Get user balance for a token
-    pub fn get_balance(e: Env, user: Address, token: Address) -> Balance {
-        storage::get_balance(&e, &user, &token)
-    }
+    /// Update the TTL policy (config manager only)
+    /// Thresholds can't exceed their extension, which must lie between the network's
+    /// minimum (MIN_TTL_EXTEND_TO) and maximum TTL
+    /// @param config: Thresholds and extensions, in ledgers, for persistent entries and the instance
+    #[only_role(operator, "config_manager")]
+    pub fn set_ttl_config(e: Env, operator: Address, config: TtlConfig) -> Result<(), Error> {
+        let max_ttl = e.storage().max_ttl();
+        if config.persistent_threshold > config.persistent_extend_to
+            || config.instance_threshold > config.instance_extend_to
+            || config.persistent_extend_to > max_ttl
+            || config.instance_extend_to > max_ttl
+            || config.persistent_extend_to < MIN_TTL_EXTEND_TO
+            || config.instance_extend_to < MIN_TTL_EXTEND_TO
+        {
+            return Err(Error::InvalidConfig);
+        }
 
-    /// Get all intent IDs for a user
-    pub fn get_user_intents(e: Env, user: Address) -> soroban_sdk::Vec<u64> {
-        storage::get_user_intents(&e, &user)
+        storage::set_ttl_config(&e, &config);
+        storage::extend_instance(&e);
+
+        events::TtlConfigUpdated { config }.publish(&e);
+        Ok(())
     }
 
-    /// Get contract admin
-    pub fn get_admin(e: Env) -> Option<Address> {
-        storage::get_admin(&e)
+    /// Get the config of a token (None if it was never listed)
+    /// Delisted tokens keep their config so existing intents are priced as before
+    pub fn get_token_config(e: Env, token: Address) -> Option<TokenConfig> {
+        storage::get_token_config(&e, &token)
     }
 
-    /// Emergency pause - admin can cancel any intent
-    /// This is a safety mechanism
-    pub fn admin_cancel_intent(e: Env, intent_id: u64, admin: Address) -> Result<(), Error> {
-        admin.require_auth();
+    /// List every token intents can be created for
+    pub fn get_supported_tokens(e: Env) -> Vec<Address> {
+        storage::get_supported_tokens(&e)
+    }
 
-        let stored_admin = storage::get_admin(&e).ok_or(Error::Unauthorized)?;
-        if admin != stored_admin {
-            return Err(Error::Unauthorized);
+    /// List a token, or update its config (config manager only)
+    /// Only listed tokens can be sold or bought by new intents
+    /// @param config: Order size bounds (in sell_token units), oracle asset and optional oracle
+    #[only_role(operator, "config_manager")]
+    pub fn set_token_config(
+        e: Env,
+        operator: Address,
+        token: Address,
+        config: TokenConfig,
+    ) -> Result<(), Error> {
+        if config.min_order_size <= 0 || config.max_order_size < config.min_order_size {
+            return Err(Error::InvalidConfig);
         }
 
-        // Get intent
-        let mut intent = storage::get_intent(&e, intent_id)
-            .ok_or(Error::IntentNotFound)?;
+        // Cache the token's decimals, so pricing never queries the token contract
+        let decimals = token::Client::new(&e, &token).decimals();
+        storage::set_token_config(&e, &token, &config);
+        storage::set_token_decimals(&e, &token, decimals);
 
-        if intent.status != IntentStatus::Active {
-            return Err(Error::IntentAlreadyExecuted);
+        events::TokenListed { token, config }.publish(&e);
+
+        Ok(())
+    }
+
+    /// Delist a token (config manager only)
+    /// Existing intents are unaffected; new intents for the token are rejected
+    #[only_role(operator, "config_manager")]
+    pub fn remove_token(e: Env, operator: Address, token: Address) -> Result<(), Error> {
+        if !storage::is_supported_token(&e, &token) {
+            return Err(Error::InvalidToken);
+        }
+
+        storage::delist_token(&e, &token);
+
+        events::TokenDelisted { token }.publish(&e);
+
+        Ok(())
+    }
+
+    /// Get the protocol fee config in effect for a token pair
+    /// (the pair's own config if set, else the default)
+    pub fn get_fee_config(e: Env, sell_token: Address, buy_token: Address) -> FeeConfig {
+        storage::get_fee_config(&e, &sell_token, &buy_token)
+    }
+
+    /// Update the default protocol fee (fee manager only)
+    /// @param config: Fee in basis points (at most MAX_FEE_BPS) and the side it is taken from
+    #[only_role(operator, "fee_manager")]
+    pub fn set_fee_config(e: Env, operator: Address, config: FeeConfig) -> Result<(), Error> {
+        if config.fee_bps > MAX_FEE_BPS {
+            return Err(Error::InvalidConfig);
+        }
+
+        storage::set_default_fee_config(&e, &config);
+
+        events::FeeConfigUpdated {
+            sell_token: None,
+            buy_token: None,
+            config,
+        }
+        .publish(&e);
+
+        Ok(())
+    }
+
+    /// Override the protocol fee for a token pair (fee manager only)
+    /// @param config: Fee in basis points (at most MAX_FEE_BPS) and the side it is taken from
+    #[only_role(operator, "fee_manager")]
+    pub fn set_pair_fee_config(
+        e: Env,
+        operator: Address,
+        sell_token: Address,
+        buy_token: Address,
+        config: FeeConfig,
+    ) -> Result<(), Error> {
+        if config.fee_bps > MAX_FEE_BPS {
+            return Err(Error::InvalidConfig);
+        }
+
+        storage::set_pair_fee_config(&e, &sell_token, &buy_token, &config);
+
+        events::FeeConfigUpdated {
+            sell_token: Some(sell_token),
+            buy_token: Some(buy_token),
+            config,
+        }
+        .publish(&e);
+
+        Ok(())
+    }
+
+    /// Remove a token pair's protocol fee override; the default applies again (fee manager only)
+    #[only_role(operator, "fee_manager")]
+    pub fn remove_pair_fee_config(
+        e: Env,
+        operator: Address,
+        sell_token: Address,
+        buy_token: Address,
+    ) -> Result<(), Error> {
+        storage::remove_pair_fee_config(&e, &sell_token, &buy_token);
+
+        events::FeeConfigUpdated {
+            sell_token: Some(sell_token),
+            buy_token: Some(buy_token),
+            config: storage::get_default_fee_config(&e),
+        }
+        .publish(&e);
+
+        Ok(())
+    }
+
+    /// Get the treasury address receiving protocol fees
+    pub fn get_treasury(e: Env) -> Option<Address> {
+        storage::get_treasury(&e)
+    }
+
+    /// Update the treasury address receiving protocol fees (fee manager only)
+    #[only_role(operator, "fee_manager")]
+    pub fn set_treasury(e: Env, operator: Address, treasury: Address) -> Result<(), Error> {
+        storage::set_treasury(&e, &treasury);
+
+        events::TreasuryUpdated { treasury }.publish(&e);
+
+        Ok(())
+    }
+
+    /// Get protocol fees accrued in a token and not yet claimed
+    pub fn get_accrued_fees(e: Env, token: Address) -> i128 {
+        storage::get_accrued_fees(&e, &token)
+    }
+
+    /// Send the protocol fees accrued in a token to the treasury
+    /// Anyone can call this; the fees can only go to the configured treasury
+    /// @param token: Token whose accrued fees are claimed
+    /// @returns: Amount sent to the treasury
+    pub fn claim_fees(e: Env, token: Address) -> Result<i128, Error> {
+        let treasury = storage::get_treasury(&e).ok_or(Error::InvalidConfig)?;
+
+        let amount = storage::get_accrued_fees(&e, &token);
+        if amount > 0 {
+            storage::set_accrued_fees(&e, &token, 0);
+
+            let client = token::Client::new(&e, &token);
+            client.transfer(&e.current_contract_address(), &treasury, &amount);
+
+            events::FeesClaimed {
+                token,
+                treasury,
+                amount,
+            }
+            .publish(&e);
+        }
+
+        Ok(amount)
+    }
+
+    /// Helper function for executors to check if an intent is executable
+    /// An intent is not executable while the oracle price is stale, nor between
+    /// the tranche windows of a recurring intent
+    /// @param intent_id: ID of the intent to check
+    /// @returns: (is_executable, current_market_buy_amount, price_timestamp)
+    pub fn check_intent_executable(
+        e: Env,
+        intent_id: u64,
+    ) -> Result<(bool, i128, u64), Error> {
+        let mut intent = storage::get_intent(&e, intent_id)
+            .ok_or(Error::IntentNotFound)?;
+
+        // Check if intent is active
+        if !intent.is_open() {
+            return Ok((false, 0, 0));
+        }
+
+        // Check expiry
+        let current_time = e.ledger().timestamp();
+        if current_time > intent.expiry {
+            return Ok((false, 0, 0));
+        }
+
+        // Recurring intents wait for their next tranche window
+        let fill_amount = intent.fillable_amount(current_time);
+        if fill_amount == 0 {
+            return Ok((false, 0, 0));
+        }
+
+        // Trailing stops trigger relative to the latest high-water mark (not stored here)
+        Self::refresh_high_water_mark(&e, &mut intent)?;
+
+        // Oracle and asset pricing each token
+        let sell_feed = Self::price_feed(&e, &intent.sell_token)?;
+        let buy_feed = Self::price_feed(&e, &intent.buy_token)?;
+
+        // Check if price condition is met using Oracle
+        let (condition_met, current_price, price_timestamp) = crate::oracle::check_price_trigger(
+            &e,
+            &sell_feed,
+            &buy_feed,
+            intent.trigger_price()?,
+            &intent.trigger_direction,
+            &intent.price_source,
+            storage::get_max_price_age(&e),
+        )?;
+
+        // Calculate estimated buy amount (in buy_token units) based on current price,
+        // as execute_intents would deliver it
+        // price_ratio = (sell_price / buy_price) * PRICE_SCALE, in whole tokens
+        let estimated_buy_amount = Self::quote_fill(&e, &intent, fill_amount, current_price)?;
+
+        Ok((condition_met, estimated_buy_amount, price_timestamp))
+    }
+
+    /// Raise a trailing stop's high-water mark to the highest oracle price
+    /// observed since creation. Anyone can call this
+    ///
+    /// Spot sources scan the oracle's retained history, so a peak between two
+    /// pokes is not missed; TWAP sources only sample the current TWAP.
+    /// Executions refresh the high-water mark too, poking just keeps the
+    /// stored stop price current for executors and UIs.
+    ///
+    /// @param intent_id: ID of the trailing stop intent
+    /// @returns: The (possibly updated) high-water mark
+    pub fn poke_intent(e: Env, intent_id: u64) -> Result<i128, Error> {
+        let mut intent = storage::get_intent(&e, intent_id)
+            .ok_or(Error::IntentNotFound)?;
+
+        Self::ensure_open(&intent)?;
+        if intent.is_expired(e.ledger().timestamp()) {
+            return Err(Error::IntentExpired);
+        }
+        if !matches!(intent.trigger_direction, TriggerDirection::TrailingStop(_)) {
+            return Err(Error::NotTrailingStop);
+        }
+
+        if Self::refresh_high_water_mark(&e, &mut intent)? {
+            storage::set_intent(&e, intent_id, &intent);
+
+            events::HighWaterMarkUpdated {
+                intent_id,
+                high_water_mark: intent.high_water_mark,
+                stop_price: intent.trigger_price()?,
+            }
+            .publish(&e);
+        }
+
+        Ok(intent.high_water_mark)
+    }
+
+    /// Cancel an active intent
+    /// Only the creator can cancel their own intent
+    /// @param intent_id: ID of the intent to cancel
+    /// @param creator: Address of the intent creator
+    pub fn cancel_intent(e: Env, intent_id: u64, creator: Address) -> Result<(), Error> {
+        creator.require_auth();
+
+        Self::cancel_by_creator(&e, intent_id, &creator)
+    }
+
+    /// Cancel several of your intents in one call
+    /// @param creator: Address of the intents' creator
+    /// @param intent_ids: IDs of the intents to cancel (1 to MAX_BATCH_SIZE)
+    /// @param mode: AllOrNothing reverts if any intent can't be cancelled;
+    ///              BestEffort skips those
+    /// @returns: Per-intent result, in order
+    pub fn cancel_intents(
+        e: Env,
+        creator: Address,
+        intent_ids: Vec<u64>,
+        mode: BatchMode,
+    ) -> Result<Vec<Result<(), Error>>, Error> {
+        creator.require_auth();
+        Self::ensure_batch_size(intent_ids.len())?;
+
+        let mut results = Vec::new(&e);
+        for intent_id in intent_ids.iter() {
+            let result = Self::cancel_by_creator(&e, intent_id, &creator);
+            results.push_back(Self::batch_result(&mode, result)?);
+        }
+        Ok(results)
+    }
+
+    /// Modify an unfilled intent in place, keeping its ID
+    /// The new terms go through the same validation as create_intent, and the
+    /// difference in sell_amount + incentive is locked or released atomically.
+    /// Partially filled, grouped and recurring intents can't be modified.
+    /// A trailing stop's high-water mark never moves down.
+    /// @param intent_id: ID of the intent to modify
+    /// @param creator: Address of the intent creator
+    /// @param new_target_price: Target price in whole buy tokens per whole sell token
+    ///                          (scaled by PRICE_SCALE)
+    /// @param new_min_buy: Minimum amount of buy_token to receive
+    /// @param new_incentive: Reward for executor (maximum incentive for ramping incentives)
+    /// @param new_expiry: Expiration timestamp
+    /// @param new_sell_amount: Amount of sell_token to sell
+    pub fn update_intent(
+        e: Env,
+        intent_id: u64,
+        creator: Address,
+        new_target_price: i128,
+        new_min_buy: i128,
+        new_incentive: i128,
+        new_expiry: u64,
+        new_sell_amount: i128,
+    ) -> Result<(), Error> {
+        creator.require_auth();
+        Self::ensure_not_paused(&e, PauseScope::NewIntents)?;
+
+        let mut intent = storage::get_intent(&e, intent_id)
+            .ok_or(Error::IntentNotFound)?;
+        if intent.creator != creator {
+            return Err(Error::Unauthorized);
+        }
+        Self::ensure_open(&intent)?;
+        if intent.filled_sell_amount > 0 || intent.group_id.is_some() || intent.is_recurring() {
+            return Err(Error::IntentNotModifiable);
+        }
+
+        Self::validate_intent(
+            &e,
+            &IntentParams {
+                sell_token: intent.sell_token.clone(),
+                sell_amount: new_sell_amount,
+                buy_token: intent.buy_token.clone(),
+                min_buy_amount: new_min_buy,
+                target_price: new_target_price,
+                incentive: new_incentive,
+                expiry: new_expiry,
+                price_source: intent.price_source.clone(),
+                trigger_direction: intent.trigger_direction.clone(),
+            },
+        )?;
+        if new_incentive < intent.min_incentive && intent.incentive_curve != IncentiveCurve::Fixed {
+            return Err(Error::InvalidAmount);
+        }
+
+        // Lock the increase, or release the decrease, of sell_amount + incentive
+        let delta = math::sub(
+            math::add(new_sell_amount, new_incentive)?,
+            intent.remaining_locked()?,
+        )?;
+        if delta > 0 {
+            Self::lock_funds(&e, &creator, &intent.sell_token, delta)?;
+        } else if delta < 0 {
+            let mut balance = storage::get_balance(&e, &creator, &intent.sell_token);
+            balance.locked = math::add(balance.locked, delta)?;
+            balance.available = math::sub(balance.available, delta)?;
+            storage::set_balance(&e, &creator, &intent.sell_token, &balance);
+        }
+
+        // Move the intent to its new place in the order book
+        if new_target_price != intent.target_price {
+            storage::remove_from_order_book(
+                &e,
+                &intent.sell_token,
+                &intent.buy_token,
+                intent_id,
+                intent.target_price,
+            );
+            storage::add_to_order_book(
+                &e,
+                &intent.sell_token,
+                &intent.buy_token,
+                OrderBookEntry {
+                    intent_id,
+                    target_price: new_target_price,
+                },
+            );
+        }
+
+        if intent.incentive_curve == IncentiveCurve::Fixed {
+            intent.min_incentive = new_incentive;
+        }
+        if matches!(intent.trigger_direction, TriggerDirection::TrailingStop(_)) {
+            intent.high_water_mark = intent.high_water_mark.max(new_target_price);
+        }
+        intent.sell_amount = new_sell_amount;
+        intent.min_buy_amount = new_min_buy;
+        intent.target_price = new_target_price;
+        intent.incentive = new_incentive;
+        intent.expiry = new_expiry;
+        storage::set_intent(&e, intent_id, &intent);
+
+        events::IntentUpdated {
+            intent_id,
+            creator,
+            sell_amount: new_sell_amount,
+            min_buy_amount: new_min_buy,
+            target_price: new_target_price,
+            incentive: new_incentive,
+            expiry: new_expiry,
+        }
+        .publish(&e);
+
+        Ok(())
+    }
+
+    /// Cancel every open intent of a price ladder
+    /// Filled and already closed levels are left as they are
+    /// @param ladder_id: ID of the ladder to cancel
+    /// @param creator: Address of the ladder creator
+    /// @returns: Number of intents cancelled
+    pub fn cancel_ladder(e: Env, ladder_id: u64, creator: Address) -> Result<u32, Error> {
+        creator.require_auth();
+
+        let ladder = storage::get_ladder(&e, ladder_id).ok_or(Error::LadderNotFound)?;
+        if ladder.creator != creator {
+            return Err(Error::OnlyCreatorCanCancel);
+        }
+
+        let mut cancelled = 0;
+        for intent_id in ladder.intent_ids.iter() {
+            let is_open = storage::get_intent(&e, intent_id).is_some_and(|intent| intent.is_open());
+            if is_open {
+                Self::cancel_by_creator(&e, intent_id, &creator)?;
+                cancelled += 1;
+            }
+        }
+
+        events::LadderCancelled {
+            ladder_id,
+            creator,
+            cancelled,
+        }
+        .publish(&e);
+
+        Ok(cancelled)
+    }
+
+    /// Sweep expired intents, refunding their locked funds to the creators' available balance
+    /// Anyone can call this; the caller receives the keeper bounty (a share of each
+    /// intent's unpaid incentive). IDs that are unknown, closed or not yet expired are skipped.
+    /// @param keeper: Address of the caller (receives the bounty)
+    /// @param intent_ids: IDs of the intents to sweep
+    /// @returns: Number of intents swept
+    pub fn sweep_expired(e: Env, keeper: Address, intent_ids: Vec<u64>) -> Result<u32, Error> {
+        keeper.require_auth();
+
+        let current_time = e.ledger().timestamp();
+        let bounty_bps = storage::get_keeper_bounty_bps(&e) as i128;
+        let mut total_bounty: Map<Address, i128> = Map::new(&e);
+        let mut swept = 0;
+
+        for intent_id in intent_ids.iter() {
+            let Some(mut intent) = storage::get_intent(&e, intent_id) else {
+                continue;
+            };
+            if !intent.is_expired(current_time) {
+                continue;
+            }
+
+            // Refund everything except the keeper bounty; group members only
+            // release (and pay bounties from) what their siblings no longer need
+            let (total_locked, group) = Self::releasable_lock(&e, &intent)?;
+            let bounty =
+                math::mul_div_floor(intent.remaining_incentive()?, bounty_bps, BPS_DENOMINATOR)?
+                    .min(total_locked);
+            let refunded = math::sub(total_locked, bounty)?;
+
+            let mut balance = storage::get_balance(&e, &intent.creator, &intent.sell_token);
+            balance.locked = math::sub(balance.locked, total_locked)?;
+            balance.available = math::add(balance.available, refunded)?;
+            storage::set_balance(&e, &intent.creator, &intent.sell_token, &balance);
+            if let Some(group) = group {
+                storage::set_intent_group(&e, &group);
+            }
+
+            if bounty > 0 {
+                let token_bounty = total_bounty.get(intent.sell_token.clone()).unwrap_or(0);
+                total_bounty.set(intent.sell_token.clone(), math::add(token_bounty, bounty)?);
+            }
+
+            intent.status = IntentStatus::Expired;
+            storage::set_intent(&e, intent_id, &intent);
+            Self::remove_from_indexes(&e, &intent);
+
+            events::IntentExpired {
+                intent_id,
+                creator: intent.creator,
+                keeper: keeper.clone(),
+                refunded,
+                bounty,
+            }
+            .publish(&e);
+
+            swept += 1;
+        }
+
+        // Pay the keeper bounty, one transfer per token
+        for (token, amount) in total_bounty.iter() {
+            let client = token::Client::new(&e, &token);
+            client.transfer(&e.current_contract_address(), &keeper, &amount);
+        }
+
+        Ok(swept)
+    }
+
+    /// Extend the TTL of a user's balances and of intents so they are not archived
+    /// Anyone can call this, e.g. a keeper looking after long-dated intents. Archived
+    /// entries must first be restored (RestoreFootprint); missing entries are skipped.
+    /// @param user: Owner of the balances (and intent index) to extend
+    /// @param tokens: Tokens whose vault balance of `user`, config and cached decimals are extended
+    /// @param intent_ids: IDs of the intents to extend (of any creator), with their groups
+    /// and their pair's fee config
+    /// @returns: Number of entries extended
+    pub fn bump(e: Env, user: Address, tokens: Vec<Address>, intent_ids: Vec<u64>) -> u32 {
+        storage::extend_instance(&e);
+
+        let mut bumped = 0;
+        if storage::bump_user_intents(&e, &user) {
+            bumped += 1;
+        }
+        for token in tokens.iter() {
+            if storage::bump_balance(&e, &user, &token) {
+                bumped += 1;
+            }
+            bumped += storage::bump_token(&e, &token);
+        }
+        for intent_id in intent_ids.iter() {
+            let Some(intent) = storage::get_intent(&e, intent_id) else {
+                continue;
+            };
+            if storage::bump_intent(&e, intent_id) {
+                bumped += 1;
+            }
+            if let Some(group_id) = intent.group_id {
+                if storage::bump_intent_group(&e, group_id) {
+                    bumped += 1;
+                }
+            }
+            if storage::bump_pair_fee_config(&e, &intent.sell_token, &intent.buy_token) {
+                bumped += 1;
+            }
+        }
+
+        bumped
+    }
+
+    /// Get intent details
+    /// Open intents past their expiry are reported as Expired, even before being swept
+    pub fn get_intent(e: Env, intent_id: u64) -> Option<Intent> {
+        let mut intent = storage::get_intent(&e, intent_id)?;
+        if intent.is_expired(e.ledger().timestamp()) {
+            intent.status = IntentStatus::Expired;
+        }
+        Some(intent)
+    }
+
+    /// Get the incentive an intent currently pays for its whole sell amount
+    /// Ramping (Dutch auction) incentives rise from min_incentive to incentive by expiry
+    pub fn get_current_incentive(e: Env, intent_id: u64) -> Result<i128, Error> {
+        let intent = storage::get_intent(&e, intent_id)
+            .ok_or(Error::IntentNotFound)?;
+        intent.incentive_at(e.ledger().timestamp())
+    }
+
+    /// Get a one-cancels-other intent group
+    pub fn get_intent_group(e: Env, group_id: u64) -> Option<IntentGroup> {
+        storage::get_intent_group(&e, group_id)
+    }
+
+    /// Get a price ladder
+    pub fn get_ladder(e: Env, ladder_id: u64) -> Option<Ladder> {
+        storage::get_ladder(&e, ladder_id)
+    }
+
+    /// Get user balance for a token
+    pub fn get_balance(e: Env, user: Address, token: Address) -> Balance {
+        storage::get_balance(&e, &user, &token)
+    }
+
+    /// Get all intent IDs for a user
+    pub fn get_user_intents(e: Env, user: Address) -> soroban_sdk::Vec<u64> {
+        storage::get_user_intents(&e, &user)
+    }
+
+    /// List active intent IDs (including partially filled), in ascending order
+    /// @param start_after: Only return IDs greater than this (pagination cursor)
+    /// @param limit: Maximum number of IDs to return (capped at MAX_PAGE_SIZE)
+    pub fn list_active_intents(e: Env, start_after: Option<u64>, limit: u32) -> Vec<u64> {
+        storage::get_active_intents(&e, start_after, limit.min(MAX_PAGE_SIZE))
+    }
+
+    /// Get the active intents for a token pair, sorted by target price (lowest first)
+    /// @param sell_token: Token being sold by the intents
+    /// @param buy_token: Token being bought by the intents
+    /// @param start_after: ID of the last intent of the previous page (pagination cursor);
+    /// if it has left the book since, the page starts after its target price
+    /// @param limit: Maximum number of intents to return (capped at MAX_PAGE_SIZE)
+    pub fn get_order_book(
+        e: Env,
+        sell_token: Address,
+        buy_token: Address,
+        start_after: Option<u64>,
+        limit: u32,
+    ) -> Vec<Intent> {
+        let mut intents = Vec::new(&e);
+        let cursor = match start_after {
+            Some(intent_id) => match storage::get_intent(&e, intent_id) {
+                Some(intent) => Some(OrderBookEntry {
+                    intent_id,
+                    target_price: intent.target_price,
+                }),
+                None => return intents,
+            },
+            None => None,
+        };
+
+        let book = storage::get_order_book(
+            &e,
+            &sell_token,
+            &buy_token,
+            cursor,
+            limit.min(MAX_PAGE_SIZE),
+        );
+        for entry in book.iter() {
+            if let Some(intent) = storage::get_intent(&e, entry.intent_id) {
+                intents.push_back(intent);
+            }
         }
 
-        // Unlock funds
+        intents
+    }
+
+    /// Pause part of the contract (guardian only)
+    /// Withdrawals and cancellations always stay available; `pause` stops every scope at once
+    /// @param scope: NewIntents, Executions or Deposits
+    #[only_role(guardian, "guardian")]
+    pub fn pause_scope(e: Env, guardian: Address, scope: PauseScope) -> Result<(), Error> {
+        storage::set_scope_paused(&e, &scope, true);
+
+        events::PauseScopeUpdated {
+            scope,
+            paused: true,
+            guardian,
+        }
+        .publish(&e);
+
+        Ok(())
+    }
+
+    /// Resume part of the contract paused with `pause_scope` (guardian only)
+    /// @param scope: NewIntents, Executions or Deposits
+    #[only_role(guardian, "guardian")]
+    pub fn unpause_scope(e: Env, guardian: Address, scope: PauseScope) -> Result<(), Error> {
+        storage::set_scope_paused(&e, &scope, false);
+
+        events::PauseScopeUpdated {
+            scope,
+            paused: false,
+            guardian,
+        }
+        .publish(&e);
+
+        Ok(())
+    }
+
+    /// Whether a scope is paused, on its own or by the global pause
+    pub fn is_paused(e: Env, scope: PauseScope) -> bool {
+        pausable::paused(&e) || storage::is_scope_paused(&e, &scope)
+    }
+
+    /// Upgrade the contract to new wasm (admin only)
+    /// Call `migrate` afterwards if the new version changes the storage schema
+    /// @param new_wasm_hash: Hash of the uploaded wasm
+    #[only_admin]
+    pub fn upgrade(e: Env, new_wasm_hash: BytesN<32>) {
+        events::ContractUpgraded {
+            new_wasm_hash: new_wasm_hash.clone(),
+        }
+        .publish(&e);
+
+        e.deployer().update_current_contract_wasm(new_wasm_hash);
+    }
+
+    /// Migrate intents and balances written under an older schema version (admin only)
+    /// Rewrites one batch of intents, with their creators' balances, per call
+    /// Returns the next intent ID to migrate, or None once the schema version is current
+    /// @param start_id: First intent ID of the batch
+    /// @param limit: Maximum number of intents to rewrite (1..=MAX_PAGE_SIZE)
+    #[only_admin]
+    pub fn migrate(e: Env, start_id: u64, limit: u32) -> Result<Option<u64>, Error> {
+        let from_version = storage::get_schema_version(&e);
+        if from_version >= SCHEMA_VERSION {
+            return Err(Error::AlreadyMigrated);
+        }
+        if limit == 0 || limit > MAX_PAGE_SIZE {
+            return Err(Error::InvalidConfig);
+        }
+
+        let intent_count = storage::get_intent_count(&e);
+        let end_id = start_id.saturating_add(limit as u64).min(intent_count);
+        for intent_id in start_id..end_id {
+            Self::migrate_intent(&e, intent_id);
+        }
+
+        if end_id < intent_count {
+            return Ok(Some(end_id));
+        }
+
+        storage::set_schema_version(&e, SCHEMA_VERSION);
+
+        events::SchemaMigrated {
+            from_version,
+            to_version: SCHEMA_VERSION,
+        }
+        .publish(&e);
+
+        Ok(None)
+    }
+
+    /// Storage schema version of the intent and balance records
+    pub fn get_schema_version(e: Env) -> u32 {
+        storage::get_schema_version(&e)
+    }
+
+    /// Emergency cancel - the guardian can cancel any intent
+    /// This is a safety mechanism
+    #[only_role(guardian, "guardian")]
+    pub fn admin_cancel_intent(e: Env, intent_id: u64, guardian: Address) -> Result<(), Error> {
+        // Get intent
+        let mut intent = storage::get_intent(&e, intent_id)
+            .ok_or(Error::IntentNotFound)?;
+
+        Self::ensure_open(&intent)?;
+
+        // Unlock the unfilled funds (group members keep what their siblings need)
         let mut balance = storage::get_balance(&e, &intent.creator, &intent.sell_token);
-        let total_locked = intent.sell_amount + intent.incentive;
-        balance.locked -= total_locked;
-        balance.available += total_locked;
+        let (total_locked, group) = Self::releasable_lock(&e, &intent)?;
+        balance.locked = math::sub(balance.locked, total_locked)?;
+        balance.available = math::add(balance.available, total_locked)?;
         storage::set_balance(&e, &intent.creator, &intent.sell_token, &balance);
+        if let Some(group) = group {
+            storage::set_intent_group(&e, &group);
+        }
 
         // Update intent
         intent.status = IntentStatus::Cancelled;
         storage::set_intent(&e, intent_id, &intent);
+        Self::remove_from_indexes(&e, &intent);
+
+        events::AdminCancel {
+            intent_id,
+            creator: intent.creator,
+            admin: guardian,
+            refunded: total_locked,
+        }
+        .publish(&e);
 
         Ok(())
     }
@@ -443,10 +1608,9 @@ impl LimitOrderContract {
         e: Env,
         token: Address,
     ) -> Result<Option<crate::oracle::PriceData>, Error> {
-        let oracle = storage::get_oracle(&e).ok_or(Error::Unauthorized)?;
-        let reflector = crate::oracle::ReflectorClient::new(&e, &oracle);
-        let asset = crate::oracle::stellar_asset(token);
-        Ok(reflector.lastprice(&aset))
+        let feed = Self::price_feed(&e, &token)?;
+        let reflector = crate::oracle::ReflectorClient::new(&e, &feed.oracle);
+        Ok(reflector.lastprice(&feed.asset))
     }
 
     /// Get the oracle decimals (precision)
@@ -462,16 +1626,18 @@ impl LimitOrderContract {
     /// This is useful for checking if a token pair has a price feed
     /// @param sell_token: First token address
     /// @param buy_token: Second token address
-    /// @returns: Cross-rate price data if available
+    /// @returns: Cross-rate price data if available (None if the tokens use different oracles)
     pub fn get_token_cross_rate(
         e: Env,
         sell_token: Address,
         buy_token: Address,
     ) -> Result<Option<crate::oracle::PriceData>, Error> {
-        let oracle = storage::get_oracle(&e).ok_or(Error::Unauthorized)?;
-        let sell_asset = crate::oracle::stellar_asset(sell_token);
-        let buy_asset = crate::oracle::stellar_asset(buy_token);
-        Ok(crate::oracle::get_cross_rate(&e, &oracle, &sell_asset, &buy_asset))
+        let sell_feed = Self::price_feed(&e, &sell_token)?;
+        let buy_feed = Self::price_feed(&e, &buy_token)?;
+        if sell_feed.oracle != buy_feed.oracle {
+            return Ok(None);
+        }
+        Ok(crate::oracle::get_cross_rate(&e, &sell_feed.oracle, &sell_feed.asset, &buy_feed.asset))
     }
 
     /// Get TWAP (Time-Weighted Average Price) for a token
@@ -484,9 +1650,778 @@ impl LimitOrderContract {
         token: Address,
         records: u32,
     ) -> Result<Option<i128>, Error> {
-        let oracle = storage::get_oracle(&e).ok_or(Error::Unauthorized)?;
-        let reflector = crate::oracle::ReflectorClient::new(&e, &oracle);
-        let asset = crate::oracle::stellar_asset(token);
-        Ok(reflector.twap(&asset, &records))
+        let feed = Self::price_feed(&e, &token)?;
+        let reflector = crate::oracle::ReflectorClient::new(&e, &feed.oracle);
+        Ok(reflector.twap(&feed.asset, &records))
+    }
+}
+
+// Role management and two-step admin transfer
+// (transfer_admin_role / accept_admin_transfer)
+#[default_impl]
+#[contractimpl]
+impl AccessControl for LimitOrderContract {}
+
+// Global pause of every scope (guardian only)
+#[contractimpl]
+impl Pausable for LimitOrderContract {
+    fn paused(e: &Env) -> bool {
+        pausable::paused(e)
+    }
+
+    #[only_role(caller, "guardian")]
+    fn pause(e: &Env, caller: Address) {
+        pausable::pause(e);
+    }
+
+    #[only_role(caller, "guardian")]
+    fn unpause(e: &Env, caller: Address) {
+        pausable::unpause(e);
+    }
+}
+
+impl LimitOrderContract {
+    /// Rewrite an intent, and its creator's balances in both tokens, into the
+    /// current layout
+    /// Every schema so far shares the layout, so records are re-saved as they are;
+    /// a version that changes it decodes the previous layout here
+    fn migrate_intent(e: &Env, intent_id: u64) {
+        let Some(intent) = storage::get_intent(e, intent_id) else {
+            return;
+        };
+        storage::set_intent(e, intent_id, &intent);
+
+        for token in [&intent.sell_token, &intent.buy_token] {
+            let balance = storage::get_balance(e, &intent.creator, token);
+            storage::set_balance(e, &intent.creator, token, &balance);
+        }
+    }
+
+    /// Oracle and asset pricing a token: its configured mapping, or the token's
+    /// Stellar asset on the default oracle
+    fn price_feed(e: &Env, token: &Address) -> Result<crate::oracle::PriceFeed, Error> {
+        let config = storage::get_token_config(e, token);
+        let oracle = match config.as_ref().and_then(|config| config.oracle.clone()) {
+            Some(oracle) => oracle,
+            None => storage::get_oracle(e).ok_or(Error::Unauthorized)?,
+        };
+        let asset = match config {
+            Some(config) => config.oracle_asset,
+            None => crate::oracle::stellar_asset(token.clone()),
+        };
+
+        Ok(crate::oracle::PriceFeed { oracle, asset })
+    }
+
+    /// Decimals of a token, as cached when it was listed
+    fn token_decimals(e: &Env, token: &Address) -> Result<u32, Error> {
+        storage::get_token_decimals(e, token).ok_or(Error::InvalidToken)
+    }
+
+    /// Powers of ten (numerator, denominator) converting a price in whole tokens to
+    /// a rate in token units, i.e. 10^buy_decimals / 10^sell_decimals reduced
+    fn unit_ratio(
+        e: &Env,
+        sell_token: &Address,
+        buy_token: &Address,
+    ) -> Result<(i128, i128), Error> {
+        let sell_decimals = Self::token_decimals(e, sell_token)?;
+        let buy_decimals = Self::token_decimals(e, buy_token)?;
+        if buy_decimals >= sell_decimals {
+            Ok((math::pow10(buy_decimals - sell_decimals)?, 1))
+        } else {
+            Ok((1, math::pow10(sell_decimals - buy_decimals)?))
+        }
+    }
+
+    /// Price of `buy_amount` obtained for `sell_amount`, in whole buy tokens per
+    /// whole sell token (scaled by PRICE_SCALE) like `target_price`
+    fn execution_price(
+        e: &Env,
+        intent: &Intent,
+        sell_amount: i128,
+        buy_amount: i128,
+    ) -> Result<i128, Error> {
+        let (num, den) = Self::unit_ratio(e, &intent.sell_token, &intent.buy_token)?;
+        math::mul_div_floor(
+            buy_amount,
+            math::mul(PRICE_SCALE, den)?,
+            math::mul(sell_amount, num)?,
+        )
+    }
+
+    /// Buy token units worth `sell_amount` sell token units at `price`
+    /// (whole buy tokens per whole sell token, scaled by PRICE_SCALE)
+    fn buy_amount_at(
+        e: &Env,
+        sell_token: &Address,
+        buy_token: &Address,
+        sell_amount: i128,
+        price: i128,
+    ) -> Result<i128, Error> {
+        let (num, den) = Self::unit_ratio(e, sell_token, buy_token)?;
+        math::mul_div_floor(sell_amount, math::mul(price, num)?, math::mul(PRICE_SCALE, den)?)
+    }
+
+    /// Execute a fill of `fill_amount` for which the executor delivers `buy_amount`
+    /// A fill quoted by the contract at `quoted_price` is verified at that price;
+    /// otherwise the price is implied by the amounts
+    fn fill_intent(
+        e: &Env,
+        intent_id: u64,
+        executor: &Address,
+        fill_amount: i128,
+        buy_amount: i128,
+        quoted_price: Option<i128>,
+    ) -> Result<(), Error> {
+        // Get intent
+        let mut intent = storage::get_intent(e, intent_id)
+            .ok_or(Error::IntentNotFound)?;
+
+        // Check intent status
+        Self::ensure_open(&intent)?;
+
+        // Check expiry
+        let current_time = e.ledger().timestamp();
+        if current_time > intent.expiry {
+            return Err(Error::IntentExpired);
+        }
+
+        if fill_amount <= 0 || fill_amount > intent.remaining_sell_amount() {
+            return Err(Error::InvalidAmount);
+        }
+
+        // Recurring intents fill exactly one tranche, once its window is open
+        if intent.is_recurring() {
+            match intent.fillable_amount(current_time) {
+                0 => return Err(Error::TrancheNotReady),
+                tranche if tranche != fill_amount => return Err(Error::InvalidAmount),
+                _ => {}
+            }
+        }
+
+        // Trailing stops trigger relative to the latest high-water mark
+        Self::refresh_high_water_mark(e, &mut intent)?;
+
+        // Protocol fee, kept from the sell tokens handed to the executor or
+        // from the buy tokens delivered to the creator
+        let fee_config = storage::get_fee_config(e, &intent.sell_token, &intent.buy_token);
+        let sell_fee = fee_config.sell_fee(fill_amount)?;
+        let buy_fee = fee_config.buy_fee(buy_amount)?;
+        let swap_amount = math::sub(fill_amount, sell_fee)?;
+        let creator_amount = math::sub(buy_amount, buy_fee)?;
+
+        // Verify minimum buy amount, target price and oracle price
+        let actual_price = match quoted_price {
+            Some(price) => price,
+            None => Self::execution_price(e, &intent, swap_amount, buy_amount)?,
+        };
+        Self::verify_fill(e, &intent, fill_amount, creator_amount, actual_price)?;
+        let settlement =
+            Self::prepare_fill(e, &intent, fill_amount, creator_amount, sell_fee, buy_fee)?;
+
+        // Execute the trade flow:
+        // 1. Transfer sell tokens from vault to executor (who will swap on DEX/AMM)
+        let sell_client = token::Client::new(e, &intent.sell_token);
+        sell_client.transfer(
+            &e.current_contract_address(),
+            executor,
+            &swap_amount,
+        );
+
+        // 2. Executor must have already obtained buy_tokens from DEX and transfers to creator
+        let buy_client = token::Client::new(e, &intent.buy_token);
+        buy_client.transfer(executor, &intent.creator, &creator_amount);
+        if buy_fee > 0 {
+            buy_client.transfer(executor, e.current_contract_address(), &buy_fee);
+        }
+
+        // 3. Pay incentive share and record the fill
+        Self::apply_fill(e, executor, settlement);
+
+        Ok(())
+    }
+
+    /// Execute a fill of `fill_amount` for the buy amount it is worth at the
+    /// current oracle price, and return that buy amount
+    fn fill_intent_at_oracle_price(
+        e: &Env,
+        intent_id: u64,
+        executor: &Address,
+        fill_amount: i128,
+    ) -> Result<i128, Error> {
+        let intent = storage::get_intent(e, intent_id).ok_or(Error::IntentNotFound)?;
+        let sell_feed = Self::price_feed(e, &intent.sell_token)?;
+        let buy_feed = Self::price_feed(e, &intent.buy_token)?;
+
+        let oracle_price = crate::oracle::get_fresh_price_ratio(
+            e,
+            &sell_feed,
+            &buy_feed,
+            &intent.price_source,
+            storage::get_max_price_age(e),
+        )?;
+
+        let buy_amount = Self::quote_fill(e, &intent, fill_amount, oracle_price.price)?;
+        let quoted_price = Some(oracle_price.price);
+        Self::fill_intent(e, intent_id, executor, fill_amount, buy_amount, quoted_price)?;
+        Ok(buy_amount)
+    }
+
+    /// Buy amount an executor delivers for a fill of `fill_amount` at `price`
+    /// The executor receives the fill net of any sell-side fee, so only that part
+    /// is priced, rounded up so the creator never gets less than `price`
+    fn quote_fill(e: &Env, intent: &Intent, fill_amount: i128, price: i128) -> Result<i128, Error> {
+        let fee_config = storage::get_fee_config(e, &intent.sell_token, &intent.buy_token);
+        let swap_amount = math::sub(fill_amount, fee_config.sell_fee(fill_amount)?)?;
+        let (num, den) = Self::unit_ratio(e, &intent.sell_token, &intent.buy_token)?;
+        math::mul_div_ceil(swap_amount, math::mul(price, num)?, math::mul(PRICE_SCALE, den)?)
+    }
+
+    /// Cancel an open intent on behalf of its creator, unlocking its funds
+    fn cancel_by_creator(e: &Env, intent_id: u64, creator: &Address) -> Result<(), Error> {
+        // Get intent
+        let mut intent = storage::get_intent(e, intent_id)
+            .ok_or(Error::IntentNotFound)?;
+
+        // Verify creator
+        if intent.creator != *creator {
+            return Err(Error::OnlyCreatorCanCancel);
+        }
+
+        // Check status
+        Self::ensure_open(&intent)?;
+
+        // Unlock the unfilled funds (group members keep what their siblings need)
+        let mut balance = storage::get_balance(e, creator, &intent.sell_token);
+        let (total_locked, group) = Self::releasable_lock(e, &intent)?;
+        balance.locked = math::sub(balance.locked, total_locked)?;
+        balance.available = math::add(balance.available, total_locked)?;
+        storage::set_balance(e, creator, &intent.sell_token, &balance);
+        if let Some(group) = group {
+            storage::set_intent_group(e, &group);
+        }
+
+        // Update intent status
+        intent.status = IntentStatus::Cancelled;
+        storage::set_intent(e, intent_id, &intent);
+        Self::remove_from_indexes(e, &intent);
+
+        events::IntentCancelled {
+            intent_id,
+            creator: creator.clone(),
+            refunded: total_locked,
+        }
+        .publish(e);
+
+        Ok(())
+    }
+
+    /// Relative size of each level of a price ladder, from the lowest price
+    /// Fails if a geometric ratio shrinks a level's weight to zero
+    fn ladder_weights(
+        e: &Env,
+        steps: u32,
+        distribution: &LadderDistribution,
+    ) -> Result<Vec<i128>, Error> {
+        let mut weights = Vec::new(e);
+        let mut weight = PRICE_SCALE;
+        for level in 0..steps {
+            match distribution {
+                LadderDistribution::Linear => weights.push_back(level as i128 + 1),
+                LadderDistribution::Geometric(ratio_bps) => {
+                    if weight == 0 {
+                        return Err(Error::InvalidConfig);
+                    }
+                    weights.push_back(weight);
+                    weight = math::mul_div_floor(weight, *ratio_bps as i128, BPS_DENOMINATOR)?;
+                }
+            }
+        }
+        Ok(weights)
+    }
+
+    /// Fail unless a batch has between 1 and MAX_BATCH_SIZE items
+    fn ensure_batch_size(len: u32) -> Result<(), Error> {
+        if len == 0 || len > MAX_BATCH_SIZE {
+            return Err(Error::InvalidBatch);
+        }
+        Ok(())
+    }
+
+    /// Item result to report in a batch, or the error reverting an
+    /// all-or-nothing batch
+    fn batch_result<T>(
+        mode: &BatchMode,
+        result: Result<T, Error>,
+    ) -> Result<Result<T, Error>, Error> {
+        match (mode, result) {
+            (BatchMode::AllOrNothing, Err(error)) => Err(error),
+            (_, result) => Ok(result),
+        }
+    }
+
+    /// Fail if `scope` is paused, on its own or by the global pause
+    fn ensure_not_paused(e: &Env, scope: PauseScope) -> Result<(), Error> {
+        if pausable::paused(e) || storage::is_scope_paused(e, &scope) {
+            return Err(Error::ContractPaused);
+        }
+        Ok(())
+    }
+
+    /// Validate the parameters of a new intent
+    fn validate_intent(e: &Env, params: &IntentParams) -> Result<(), Error> {
+        if params.sell_amount <= 0 || params.min_buy_amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        // Both tokens must be listed; the order size is bounded by the sell token's config
+        if !storage::is_supported_token(e, &params.sell_token)
+            || !storage::is_supported_token(e, &params.buy_token)
+        {
+            return Err(Error::InvalidToken);
+        }
+        let sell_config =
+            storage::get_token_config(e, &params.sell_token).ok_or(Error::InvalidToken)?;
+        if params.sell_amount < sell_config.min_order_size
+            || params.sell_amount > sell_config.max_order_size
+        {
+            return Err(Error::InvalidAmount);
+        }
+        if params.target_price <= 0 {
+            return Err(Error::InvalidPrice);
+        }
+        if !crate::oracle::is_valid_price_source(&params.price_source) {
+            return Err(Error::InvalidPriceSource);
+        }
+        if let TriggerDirection::TrailingStop(trail_bps) = params.trigger_direction {
+            if trail_bps == 0 || trail_bps as i128 >= BPS_DENOMINATOR {
+                return Err(Error::InvalidPrice);
+            }
+        }
+        if params.incentive < 0 || params.incentive > params.sell_amount {
+            return Err(Error::InvalidAmount);
+        }
+
+        if params.expiry <= e.ledger().timestamp() {
+            return Err(Error::IntentExpired);
+        }
+
+        Ok(())
+    }
+
+    /// Validate a new intent, lock its funds and store it
+    fn place_intent(e: &Env, creator: &Address, params: IntentParams) -> Result<u64, Error> {
+        // Validation
+        Self::validate_intent(e, &params)?;
+
+        // Check balance and lock funds
+        let locked = math::add(params.sell_amount, params.incentive)?;
+        Self::lock_funds(e, creator, &params.sell_token, locked)?;
+
+        Ok(Self::store_intent(e, &Self::new_intent(e, creator, params)))
+    }
+
+    /// Move `amount` of the creator's available balance to locked
+    fn lock_funds(e: &Env, creator: &Address, token: &Address, amount: i128) -> Result<(), Error> {
+        let mut balance = storage::get_balance(e, creator, token);
+
+        if balance.available < amount {
+            return Err(Error::InsufficientBalance);
+        }
+
+        balance.available = math::sub(balance.available, amount)?;
+        balance.locked = math::add(balance.locked, amount)?;
+        storage::set_balance(e, creator, token, &balance);
+        Ok(())
+    }
+
+    /// Build a new Active intent from validated parameters, allocating its ID
+    /// Callers set the group, schedule or incentive curve before storing it
+    fn new_intent(e: &Env, creator: &Address, params: IntentParams) -> Intent {
+        // Trailing stops start from target_price as their high-water mark
+        let high_water_mark = match params.trigger_direction {
+            TriggerDirection::TrailingStop(_) => params.target_price,
+            _ => 0,
+        };
+
+        Intent {
+            id: storage::get_next_intent_id(e),
+            creator: creator.clone(),
+            sell_token: params.sell_token,
+            sell_amount: params.sell_amount,
+            buy_token: params.buy_token,
+            min_buy_amount: params.min_buy_amount,
+            target_price: params.target_price,
+            incentive: params.incentive,
+            min_incentive: params.incentive,
+            incentive_curve: IncentiveCurve::Fixed,
+            expiry: params.expiry,
+            status: IntentStatus::Active,
+            executor: None,
+            actual_buy_amount: None,
+            filled_sell_amount: 0,
+            filled_buy_amount: 0,
+            price_source: params.price_source,
+            trigger_direction: params.trigger_direction,
+            created_at: e.ledger().timestamp(),
+            high_water_mark,
+            group_id: None,
+            amount_per_interval: 0,
+            interval: 0,
+            tranches_remaining: 0,
+            // The first tranche of a recurring intent is executable right away
+            next_tranche_at: e.ledger().timestamp(),
+        }
+    }
+
+    /// Store a new intent (funds already locked), index it and emit IntentCreated
+    fn store_intent(e: &Env, intent: &Intent) -> u64 {
+        let intent_id = intent.id;
+        storage::set_intent(e, intent_id, intent);
+        storage::add_user_intent(e, &intent.creator, intent_id);
+        storage::add_active_intent(e, intent_id);
+        storage::add_to_order_book(
+            e,
+            &intent.sell_token,
+            &intent.buy_token,
+            OrderBookEntry {
+                intent_id,
+                target_price: intent.target_price,
+            },
+        );
+
+        events::IntentCreated {
+            intent_id,
+            sell_token: intent.sell_token.clone(),
+            buy_token: intent.buy_token.clone(),
+            creator: intent.creator.clone(),
+            sell_amount: intent.sell_amount,
+            min_buy_amount: intent.min_buy_amount,
+            target_price: intent.target_price,
+            incentive: intent.incentive,
+            expiry: intent.expiry,
+        }
+        .publish(e);
+
+        intent_id
+    }
+
+    /// Locked funds released by closing `intent` without filling it
+    /// A member of an unfilled group only releases what its open siblings no
+    /// longer need; the group is returned with its shared lock shrunk, for the
+    /// caller to store once the rest of its checked math has succeeded
+    fn releasable_lock(e: &Env, intent: &Intent) -> Result<(i128, Option<IntentGroup>), Error> {
+        let group = intent.group_id.and_then(|group_id| storage::get_intent_group(e, group_id));
+        let Some(mut group) = group.filter(|group| group.filled_intent.is_none()) else {
+            return Ok((intent.remaining_locked()?, None));
+        };
+
+        let mut required = 0;
+        for sibling_id in group.intent_ids.iter() {
+            if sibling_id == intent.id {
+                continue;
+            }
+            if let Some(sibling) = storage::get_intent(e, sibling_id) {
+                if sibling.is_open() {
+                    required = required.max(sibling.remaining_locked()?);
+                }
+            }
+        }
+
+        let released = math::sub(group.locked, required)?;
+        group.locked = required;
+        Ok((released, Some(group)))
+    }
+
+    /// On the first fill of a group member, cancel its siblings and mark the
+    /// group filled; `released` (computed by prepare_fill) is already counted
+    /// in the creator's balance
+    fn close_group(e: &Env, intent: &Intent, mut group: IntentGroup, released: i128) {
+        for sibling_id in group.intent_ids.iter() {
+            if sibling_id == intent.id {
+                continue;
+            }
+            let Some(mut sibling) = storage::get_intent(e, sibling_id) else {
+                continue;
+            };
+            if !sibling.is_open() {
+                continue;
+            }
+
+            sibling.status = IntentStatus::Cancelled;
+            storage::set_intent(e, sibling_id, &sibling);
+            Self::remove_from_indexes(e, &sibling);
+
+            events::IntentCancelled {
+                intent_id: sibling_id,
+                creator: sibling.creator,
+                refunded: 0,
+            }
+            .publish(e);
+        }
+
+        group.locked = 0;
+        group.filled_intent = Some(intent.id);
+        storage::set_intent_group(e, &group);
+
+        events::IntentGroupFilled {
+            group_id: group.id,
+            intent_id: intent.id,
+            released,
+        }
+        .publish(e);
+    }
+
+    /// Fail with the error matching a closed intent's status
+    fn ensure_open(intent: &Intent) -> Result<(), Error> {
+        match intent.status {
+            IntentStatus::Active | IntentStatus::PartiallyFilled => Ok(()),
+            IntentStatus::Executed => Err(Error::IntentAlreadyExecuted),
+            IntentStatus::Cancelled => Err(Error::IntentCancelled),
+            IntentStatus::Expired => Err(Error::IntentExpired),
+        }
+    }
+
+    /// Remove a closed intent from the active and order book indexes
+    fn remove_from_indexes(e: &Env, intent: &Intent) {
+        storage::remove_active_intent(e, intent.id);
+        storage::remove_from_order_book(
+            e,
+            &intent.sell_token,
+            &intent.buy_token,
+            intent.id,
+            intent.target_price,
+        );
+    }
+
+    /// Raise a trailing stop's high-water mark to the highest fresh oracle
+    /// price observed since the intent was created
+    /// Returns whether the high-water mark changed; other intents are left as is
+    fn refresh_high_water_mark(e: &Env, intent: &mut Intent) -> Result<bool, Error> {
+        if !matches!(intent.trigger_direction, TriggerDirection::TrailingStop(_)) {
+            return Ok(false);
+        }
+
+        let sell_feed = Self::price_feed(e, &intent.sell_token)?;
+        let buy_feed = Self::price_feed(e, &intent.buy_token)?;
+
+        let mut high_water_mark = intent.high_water_mark;
+
+        // Recorded history since creation (spot sources only)
+        let history = crate::oracle::get_price_ratio_history(
+            e,
+            &sell_feed,
+            &buy_feed,
+            &intent.price_source,
+        )?;
+        for record in history.iter() {
+            if record.timestamp >= intent.created_at && record.price > high_water_mark {
+                high_water_mark = record.price;
+            }
+        }
+
+        // Current price of the intent's own source
+        let current =
+            crate::oracle::get_price_ratio(e, &sell_feed, &buy_feed, &intent.price_source)?;
+        if let Some(current) = current {
+            let is_fresh =
+                !crate::oracle::is_price_stale(e, current.timestamp, storage::get_max_price_age(e));
+            if is_fresh && current.price > high_water_mark {
+                high_water_mark = current.price;
+            }
+        }
+
+        let changed = high_water_mark != intent.high_water_mark;
+        intent.high_water_mark = high_water_mark;
+        Ok(changed)
+    }
+
+    /// Verify a fill of `fill_amount` sell tokens against the pro-rata minimum,
+    /// the target price and the oracle price
+    /// The creator receives `creator_amount` (net of buy-side fee), and the sell
+    /// tokens swapped (net of sell-side fee) went at `actual_price`
+    fn verify_fill(
+        e: &Env,
+        intent: &Intent,
+        fill_amount: i128,
+        creator_amount: i128,
+        actual_price: i128,
+    ) -> Result<(), Error> {
+        // Verify minimum buy amount (what the creator actually receives)
+        if creator_amount < intent.min_buy_for_fill(fill_amount)? {
+            return Err(Error::MinBuyAmountNotMet);
+        }
+
+        // Verify price condition on the market price obtained
+        // actual_price = buy_amount / swap_amount in whole tokens (scaled by PRICE_SCALE)
+        // Stop-losses fill below target by design; min_buy_amount is their floor
+        if intent.trigger_direction == TriggerDirection::Above
+            && actual_price < intent.target_price
+        {
+            return Err(Error::PriceConditionNotMet);
+        }
+
+        // Verify against the oracle: the market must have reached the target,
+        // and the executor's implied price must be close to the oracle price
+        Self::verify_oracle_price(e, intent, actual_price)
+    }
+
+    /// Compute every storage update of a fill, before any tokens move
+    /// All checked math of a fill happens here, so a fill that fails (e.g. one
+    /// item of a BestEffort batch) has written nothing; apply_fill can't fail.
+    /// `buy_amount` is what the creator receives, net of the buy-side fee.
+    fn prepare_fill(
+        e: &Env,
+        intent: &Intent,
+        fill_amount: i128,
+        buy_amount: i128,
+        sell_fee: i128,
+        buy_fee: i128,
+    ) -> Result<FillSettlement, Error> {
+        // The first fill of a group member releases the group's shared lock
+        // down to the member's own remaining sell amount + incentive
+        let group = intent
+            .group_id
+            .and_then(|group_id| storage::get_intent_group(e, group_id))
+            .filter(|group| group.filled_intent.is_none());
+        let released = match &group {
+            Some(group) => math::sub(group.locked, intent.remaining_locked()?)?,
+            None => 0,
+        };
+
+        // The executor earns the fill's share of the locked incentive, scaled
+        // down to its current value for ramping incentives
+        let locked_incentive = intent.incentive_for_fill(fill_amount)?;
+        let incentive = intent.incentive_payout(locked_incentive, e.ledger().timestamp())?;
+
+        // Unlock the filled funds and the released group lock, refund the unused incentive
+        let mut creator_balance = storage::get_balance(e, &intent.creator, &intent.sell_token);
+        creator_balance.locked = math::sub(
+            creator_balance.locked,
+            math::add(released, math::add(fill_amount, locked_incentive)?)?,
+        )?;
+        creator_balance.available = math::add(
+            creator_balance.available,
+            math::add(released, math::sub(locked_incentive, incentive)?)?,
+        )?;
+
+        // Record the fill
+        let mut filled = intent.clone();
+        filled.filled_sell_amount = math::add(intent.filled_sell_amount, fill_amount)?;
+        filled.filled_buy_amount = math::add(intent.filled_buy_amount, buy_amount)?;
+        if filled.is_recurring() {
+            filled.tranches_remaining -= 1;
+            filled.next_tranche_at = intent
+                .next_tranche_at
+                .checked_add(intent.interval)
+                .ok_or(Error::MathOverflow)?;
+        }
+        filled.status = if filled.remaining_sell_amount() == 0 {
+            IntentStatus::Executed
+        } else {
+            IntentStatus::PartiallyFilled
+        };
+        filled.actual_buy_amount = Some(filled.filled_buy_amount);
+
+        // Protocol fees the contract holds once the fill's tokens have moved
+        let accrued_sell_fees =
+            math::add(storage::get_accrued_fees(e, &intent.sell_token), sell_fee)?;
+        let accrued_buy_fees = math::add(storage::get_accrued_fees(e, &intent.buy_token), buy_fee)?;
+
+        Ok(FillSettlement {
+            intent: filled,
+            fill_amount,
+            buy_amount,
+            incentive,
+            creator_balance,
+            group: group.map(|group| (group, released)),
+            fees: [
+                (intent.sell_token.clone(), sell_fee, accrued_sell_fees),
+                (intent.buy_token.clone(), buy_fee, accrued_buy_fees),
+            ],
+        })
+    }
+
+    /// Write a fill computed by prepare_fill, once its tokens have moved, and
+    /// pay the executor's incentive share
+    fn apply_fill(e: &Env, executor: &Address, settlement: FillSettlement) {
+        let FillSettlement {
+            mut intent,
+            fill_amount,
+            buy_amount,
+            incentive,
+            creator_balance,
+            group,
+            fees,
+        } = settlement;
+
+        // Accrue the protocol fees the contract now holds
+        for (token, amount, accrued) in fees {
+            if amount > 0 {
+                storage::set_accrued_fees(e, &token, accrued);
+                events::FeeCollected {
+                    intent_id: intent.id,
+                    token,
+                    amount,
+                }
+                .publish(e);
+            }
+        }
+
+        // The first fill of a group member cancels its siblings
+        if let Some((group, released)) = group {
+            Self::close_group(e, &intent, group, released);
+        }
+
+        if incentive > 0 {
+            let sell_client = token::Client::new(e, &intent.sell_token);
+            sell_client.transfer(&e.current_contract_address(), executor, &incentive);
+        }
+        storage::set_balance(e, &intent.creator, &intent.sell_token, &creator_balance);
+
+        intent.executor = Some(executor.clone());
+        storage::set_intent(e, intent.id, &intent);
+        if intent.status == IntentStatus::Executed {
+            Self::remove_from_indexes(e, &intent);
+        }
+
+        events::IntentExecuted {
+            intent_id: intent.id,
+            creator: intent.creator.clone(),
+            executor: executor.clone(),
+            fill_amount,
+            buy_amount,
+            incentive,
+            status: intent.status,
+        }
+        .publish(e);
+    }
+
+    /// Verify an execution price against the configured Reflector oracle
+    /// Fails if the oracle price has not crossed the intent's target, or if the
+    /// executor's implied price deviates from it by more than the allowed bps
+    fn verify_oracle_price(e: &Env, intent: &Intent, actual_price: i128) -> Result<(), Error> {
+        let sell_feed = Self::price_feed(e, &intent.sell_token)?;
+        let buy_feed = Self::price_feed(e, &intent.buy_token)?;
+
+        let oracle_price = crate::oracle::get_fresh_price_ratio(
+            e,
+            &sell_feed,
+            &buy_feed,
+            &intent.price_source,
+            storage::get_max_price_age(e),
+        )?;
+
+        if !intent.trigger_direction.is_met(oracle_price.price, intent.trigger_price()?) {
+            return Err(Error::PriceConditionNotMet);
+        }
+
+        let max_deviation = storage::get_max_deviation_bps(e) as i128;
+        if crate::oracle::deviation_bps(actual_price, oracle_price.price)? > max_deviation {
+            return Err(Error::PriceDeviationTooHigh);
+        }
+
+        Ok(())
     }
 }
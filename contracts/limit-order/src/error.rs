@@ -32,4 +32,36 @@ pub enum Error {
     TransferFailed = 13,
     /// Minimum buy amount not met
     MinBuyAmountNotMet = 14,
+    /// Oracle has no price for one of the assets
+    OraclePriceUnavailable = 15,
+    /// Executor price deviates too far from the oracle price
+    PriceDeviationTooHigh = 16,
+    /// Invalid configuration value
+    InvalidConfig = 17,
+    /// Swap path is invalid or has no liquidity
+    InvalidPath = 18,
+    /// Invalid oracle price source
+    InvalidPriceSource = 19,
+    /// Oracle price is older than the maximum allowed age
+    StalePrice = 20,
+    /// Intent is not a trailing stop
+    NotTrailingStop = 21,
+    /// Intent group is empty, too large or not found
+    InvalidGroup = 22,
+    /// Next tranche of a recurring intent is not executable yet
+    TrancheNotReady = 23,
+    /// This part of the contract is paused
+    ContractPaused = 24,
+    /// Storage schema is already at the current version
+    AlreadyMigrated = 25,
+    /// A price or amount computation overflowed
+    MathOverflow = 26,
+    /// A price or amount computation divided by zero
+    DivisionByZero = 27,
+    /// Intent can't be modified (partially filled, grouped or recurring)
+    IntentNotModifiable = 28,
+    /// Batch is empty or larger than MAX_BATCH_SIZE
+    InvalidBatch = 29,
+    /// Price ladder not found
+    LadderNotFound = 30,
 }
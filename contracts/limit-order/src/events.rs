@@ -0,0 +1,299 @@
+use soroban_sdk::{contractevent, Address, BytesN, Vec};
+
+use crate::types::{FeeConfig, IntentStatus, PauseScope, TokenConfig, TtlConfig};
+
+// Events emitted by the limit order contract
+// Topics are limited to 4 per event (including the event name), so each event
+// carries the identifiers most useful for filtering as topics and the rest as data.
+
+/// Tokens deposited into the vault
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Deposit {
+    #[topic]
+    pub user: Address,
+    #[topic]
+    pub token: Address,
+    pub amount: i128,
+}
+
+/// Tokens withdrawn from the vault
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Withdraw {
+    #[topic]
+    pub user: Address,
+    #[topic]
+    pub token: Address,
+    pub amount: i128,
+}
+
+/// New intent created
+/// Executors can subscribe by token pair
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntentCreated {
+    #[topic]
+    pub intent_id: u64,
+    #[topic]
+    pub sell_token: Address,
+    #[topic]
+    pub buy_token: Address,
+    pub creator: Address,
+    pub sell_amount: i128,
+    pub min_buy_amount: i128,
+    pub target_price: i128,
+    pub incentive: i128,
+    pub expiry: u64,
+}
+
+/// Intent filled, fully or partially
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntentExecuted {
+    #[topic]
+    pub intent_id: u64,
+    #[topic]
+    pub creator: Address,
+    #[topic]
+    pub executor: Address,
+    pub fill_amount: i128,
+    pub buy_amount: i128,
+    pub incentive: i128,
+    pub status: IntentStatus,
+}
+
+/// One-cancels-other intent group created
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntentGroupCreated {
+    #[topic]
+    pub group_id: u64,
+    #[topic]
+    pub creator: Address,
+    pub intent_ids: Vec<u64>,
+    pub locked: i128,
+}
+
+/// Price ladder created
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LadderCreated {
+    #[topic]
+    pub ladder_id: u64,
+    #[topic]
+    pub creator: Address,
+    pub intent_ids: Vec<u64>,
+    pub total_amount: i128,
+}
+
+/// Open intents of a price ladder cancelled by its creator
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LadderCancelled {
+    #[topic]
+    pub ladder_id: u64,
+    #[topic]
+    pub creator: Address,
+    pub cancelled: u32,
+}
+
+/// First fill of a group member; its siblings are cancelled
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntentGroupFilled {
+    #[topic]
+    pub group_id: u64,
+    #[topic]
+    pub intent_id: u64,
+    pub released: i128,
+}
+
+/// Intent modified in place by its creator (new terms, same ID)
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntentUpdated {
+    #[topic]
+    pub intent_id: u64,
+    #[topic]
+    pub creator: Address,
+    pub sell_amount: i128,
+    pub min_buy_amount: i128,
+    pub target_price: i128,
+    pub incentive: i128,
+    pub expiry: u64,
+}
+
+/// Intent cancelled by its creator (or by the fill of a group sibling)
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntentCancelled {
+    #[topic]
+    pub intent_id: u64,
+    #[topic]
+    pub creator: Address,
+    pub refunded: i128,
+}
+
+/// Expired intent swept and refunded to the creator's available balance
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntentExpired {
+    #[topic]
+    pub intent_id: u64,
+    #[topic]
+    pub creator: Address,
+    pub keeper: Address,
+    pub refunded: i128,
+    pub bounty: i128,
+}
+
+/// Intent cancelled by the admin
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminCancel {
+    #[topic]
+    pub intent_id: u64,
+    #[topic]
+    pub creator: Address,
+    pub admin: Address,
+    pub refunded: i128,
+}
+
+/// Trailing stop's high-water mark raised
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HighWaterMarkUpdated {
+    #[topic]
+    pub intent_id: u64,
+    pub high_water_mark: i128,
+    pub stop_price: i128,
+}
+
+/// Soroswap router address changed
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RouterUpdated {
+    pub router: Address,
+}
+
+/// Reflector oracle address changed
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OracleUpdated {
+    pub oracle: Address,
+}
+
+/// Intermediate tokens for multi-hop routing changed
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HopTokensUpdated {
+    pub tokens: Vec<Address>,
+}
+
+/// Keeper bounty for sweeping expired intents changed
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeeperBountyUpdated {
+    pub bounty_bps: u32,
+}
+
+/// Maximum accepted oracle price age changed
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaxPriceAgeUpdated {
+    pub max_age: u64,
+}
+
+/// Maximum deviation between executor and oracle price changed
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaxDeviationUpdated {
+    pub max_deviation_bps: u32,
+}
+
+/// TTL policy changed
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TtlConfigUpdated {
+    pub config: TtlConfig,
+}
+
+/// Protocol fee charged on a fill
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeCollected {
+    #[topic]
+    pub intent_id: u64,
+    #[topic]
+    pub token: Address,
+    pub amount: i128,
+}
+
+/// Accrued protocol fees sent to the treasury
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeesClaimed {
+    #[topic]
+    pub token: Address,
+    pub treasury: Address,
+    pub amount: i128,
+}
+
+/// Protocol fee config changed, for a token pair or by default (no pair)
+/// `config` is the config now in effect
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeConfigUpdated {
+    pub sell_token: Option<Address>,
+    pub buy_token: Option<Address>,
+    pub config: FeeConfig,
+}
+
+/// Treasury address changed
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TreasuryUpdated {
+    pub treasury: Address,
+}
+
+/// Pause scope paused or resumed by a guardian
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PauseScopeUpdated {
+    pub scope: PauseScope,
+    pub paused: bool,
+    pub guardian: Address,
+}
+
+/// Contract wasm upgraded by the admin
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContractUpgraded {
+    pub new_wasm_hash: BytesN<32>,
+}
+
+/// Storage migrated to a new schema version
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SchemaMigrated {
+    pub from_version: u32,
+    pub to_version: u32,
+}
+
+/// Token listed, or its config updated
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenListed {
+    #[topic]
+    pub token: Address,
+    pub config: TokenConfig,
+}
+
+/// Token delisted
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenDelisted {
+    #[topic]
+    pub token: Address,
+}
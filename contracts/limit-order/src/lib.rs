@@ -1,11 +1,14 @@
 #![no_std]
+#![allow(clippy::too_many_arguments)]
 
 mod storage;
 mod types;
 mod contract;
 mod error;
+mod events;
 mod soroswap;
 mod oracle;
+mod math;
 
 #[cfg(test)]
 mod test;
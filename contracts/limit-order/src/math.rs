@@ -0,0 +1,121 @@
+use crate::error::Error;
+
+// Overflow-safe fixed-point arithmetic for prices and amounts
+// Every price and amount computation goes through these helpers, so an
+// overflow surfaces as a typed error instead of aborting the transaction.
+
+/// Rounding direction of a division
+/// Round down what is paid out to users, round up what is required from them
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rounding {
+    /// Towards negative infinity
+    Floor,
+    /// Towards positive infinity
+    Ceil,
+}
+
+/// `a * b / denominator`, rounded in the given direction
+/// The product is computed on 256 bits, so only a quotient that does not fit
+/// in i128 overflows
+pub fn mul_div(a: i128, b: i128, denominator: i128, rounding: Rounding) -> Result<i128, Error> {
+    if denominator == 0 {
+        return Err(Error::DivisionByZero);
+    }
+
+    // Fast path: the product fits in i128
+    if let Some(product) = a.checked_mul(b) {
+        let quotient = product / denominator;
+        let exact = product % denominator == 0;
+        let negative = (product < 0) != (denominator < 0);
+        return round(quotient, exact, negative, rounding);
+    }
+
+    let negative = ((a < 0) != (b < 0)) != (denominator < 0);
+    let (high, low) = wide_mul(a.unsigned_abs(), b.unsigned_abs());
+    let (quotient, exact) = wide_div(high, low, denominator.unsigned_abs())?;
+    let quotient = i128::try_from(quotient).map_err(|_| Error::MathOverflow)?;
+    let quotient = if negative { -quotient } else { quotient };
+    round(quotient, exact, negative, rounding)
+}
+
+/// `a * b / denominator`, rounded down
+pub fn mul_div_floor(a: i128, b: i128, denominator: i128) -> Result<i128, Error> {
+    mul_div(a, b, denominator, Rounding::Floor)
+}
+
+/// `a * b / denominator`, rounded up
+pub fn mul_div_ceil(a: i128, b: i128, denominator: i128) -> Result<i128, Error> {
+    mul_div(a, b, denominator, Rounding::Ceil)
+}
+
+/// Checked `a * b`
+pub fn mul(a: i128, b: i128) -> Result<i128, Error> {
+    a.checked_mul(b).ok_or(Error::MathOverflow)
+}
+
+/// Checked `a + b`
+pub fn add(a: i128, b: i128) -> Result<i128, Error> {
+    a.checked_add(b).ok_or(Error::MathOverflow)
+}
+
+/// Checked `a - b`
+pub fn sub(a: i128, b: i128) -> Result<i128, Error> {
+    a.checked_sub(b).ok_or(Error::MathOverflow)
+}
+
+/// Checked `10^exponent`
+pub fn pow10(exponent: u32) -> Result<i128, Error> {
+    10_i128.checked_pow(exponent).ok_or(Error::MathOverflow)
+}
+
+/// Adjust a quotient truncated towards zero to the rounding direction
+fn round(quotient: i128, exact: bool, negative: bool, rounding: Rounding) -> Result<i128, Error> {
+    if exact {
+        return Ok(quotient);
+    }
+    match (rounding, negative) {
+        (Rounding::Floor, true) => sub(quotient, 1),
+        (Rounding::Ceil, false) => add(quotient, 1),
+        _ => Ok(quotient),
+    }
+}
+
+/// Full 256-bit product of two u128, as (high, low) halves
+fn wide_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & MASK);
+    let (b_high, b_low) = (b >> 64, b & MASK);
+
+    let low_low = a_low * b_low;
+    let high_low = a_high * b_low;
+    let low_high = a_low * b_high;
+    let high_high = a_high * b_high;
+
+    let middle = (low_low >> 64) + (high_low & MASK) + (low_high & MASK);
+    let low = (low_low & MASK) | (middle << 64);
+    let high = high_high + (high_low >> 64) + (low_high >> 64) + (middle >> 64);
+    (high, low)
+}
+
+/// Divide a 256-bit (high, low) value by a u128
+/// Returns the quotient and whether the division is exact
+fn wide_div(high: u128, low: u128, divisor: u128) -> Result<(u128, bool), Error> {
+    // The quotient only fits in 128 bits if the high half is below the divisor
+    if high >= divisor {
+        return Err(Error::MathOverflow);
+    }
+
+    // Restoring long division, one bit of the low half at a time
+    let mut remainder = high;
+    let mut quotient = 0_u128;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            quotient |= 1;
+        }
+    }
+    Ok((quotient, remainder == 0))
+}
@@ -1,8 +1,16 @@
 use soroban_sdk::{contractclient, contracttype, Address, Env, Symbol, Vec};
 
+use crate::error::Error as ContractError;
+use crate::math;
+use crate::types::{
+    PriceSource, TriggerDirection, BPS_DENOMINATOR, MAX_HISTORY_RECORDS, MAX_TWAP_RECORDS,
+    PRICE_SCALE,
+};
+
 /// Reflector Oracle Interface (SEP-40 compliant)
 /// Documentation: https://reflector.network/
 /// Testnet Oracle: CAVLP5DH2GJPZMVO7IJY4CVOD5MWEFTJFVPD2YY2FQXOQHRGHK4D6HLP
+#[allow(dead_code)] // only used through the generated client
 #[contractclient(name = "ReflectorClient")]
 pub trait Contract {
     /// Base oracle symbol the price is reported in
@@ -70,78 +78,280 @@ pub struct PriceData {
     pub timestamp: u64,   // record timestamp
 }
 
-/// Possible runtime errors
-#[soroban_sdk::contracterror(export = false)]
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
-pub enum Error {
-    AlreadyInitialized = 0,
-    Unauthorized = 1,
-    AssetMissing = 2,
-    AssetAlreadyExists = 3,
-    InvalidConfigVersion = 4,
-    InvalidTimestamp = 5,
-    InvalidUpdateLength = 6,
-    AssetLimitExceeded = 7,
+/// Where a token is priced: an oracle and the asset quoting the token on it
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceFeed {
+    pub oracle: Address,
+    pub asset: Asset,
+}
+
+/// Fetch the sell/buy price ratio from Reflector Oracle
+/// Returns None if the oracle has no price for the pair, and an error if the
+/// prices are too large to compute the ratio
+///
+/// The ratio is rescaled to PRICE_SCALE to be directly comparable with
+/// `Intent::target_price`, whatever the oracle decimals. The returned
+/// timestamp is that of the oldest price used (or the oracles' last update
+/// for TWAP sources).
+///
+/// Both tokens may be priced by different oracles, which must then quote in
+/// the same base asset. Cross sources need both tokens on the same oracle.
+///
+/// @param env: Contract environment
+/// @param sell_feed: Where the asset being sold is priced
+/// @param buy_feed: Where the asset being bought is priced
+/// @param source: Which oracle price to use (spot, TWAP, cross spot or cross TWAP)
+pub fn get_price_ratio(
+    env: &Env,
+    sell_feed: &PriceFeed,
+    buy_feed: &PriceFeed,
+    source: &PriceSource,
+) -> Result<Option<PriceData>, ContractError> {
+    let sell_oracle = ReflectorClient::new(env, &sell_feed.oracle);
+    let buy_oracle = ReflectorClient::new(env, &buy_feed.oracle);
+    let (sell_scale, buy_scale) = oracle_scales(env, sell_feed, buy_feed)?;
+
+    let (sell_price_value, buy_price_value, timestamp) = match source {
+        PriceSource::Spot => {
+            let Some(sell_price) = sell_oracle.lastprice(&sell_feed.asset) else {
+                return Ok(None);
+            };
+            let Some(buy_price) = buy_oracle.lastprice(&buy_feed.asset) else {
+                return Ok(None);
+            };
+            (
+                math::mul(sell_price.price, buy_scale)?,
+                math::mul(buy_price.price, sell_scale)?,
+                sell_price.timestamp.min(buy_price.timestamp),
+            )
+        }
+        PriceSource::Twap(records) => {
+            let Some(sell_twap) = sell_oracle.twap(&sell_feed.asset, records) else {
+                return Ok(None);
+            };
+            let Some(buy_twap) = buy_oracle.twap(&buy_feed.asset, records) else {
+                return Ok(None);
+            };
+            (
+                math::mul(sell_twap, buy_scale)?,
+                math::mul(buy_twap, sell_scale)?,
+                sell_oracle.last_timestamp().min(buy_oracle.last_timestamp()),
+            )
+        }
+        // Cross prices quote sell_asset in buy_asset, scaled by the oracle decimals
+        PriceSource::CrossSpot => {
+            let cross_price = shared_oracle(sell_feed, buy_feed).and_then(|oracle| {
+                get_cross_rate(env, oracle, &sell_feed.asset, &buy_feed.asset)
+            });
+            let Some(cross_price) = cross_price else {
+                return Ok(None);
+            };
+            (
+                cross_price.price,
+                math::pow10(sell_oracle.decimals())?,
+                cross_price.timestamp,
+            )
+        }
+        PriceSource::CrossTwap(records) => {
+            let cross_twap = shared_oracle(sell_feed, buy_feed).and_then(|oracle| {
+                get_cross_rate_twap(env, oracle, &sell_feed.asset, &buy_feed.asset, *records)
+            });
+            let Some(cross_twap) = cross_twap else {
+                return Ok(None);
+            };
+            (
+                cross_twap,
+                math::pow10(sell_oracle.decimals())?,
+                sell_oracle.last_timestamp(),
+            )
+        }
+    };
+
+    if sell_price_value <= 0 || buy_price_value <= 0 {
+        return Ok(None);
+    }
+
+    // Calculate price ratio: sell_asset / buy_asset
+    // Both prices are in USD terms, so ratio gives us the exchange rate
+    // Example: If XLM = $0.12 and USDC = $1.00, then XLM/USDC = 0.12
+    Ok(Some(PriceData {
+        price: math::mul_div_floor(sell_price_value, PRICE_SCALE, buy_price_value)?,
+        timestamp,
+    }))
+}
+
+/// Fetch the recent sell/buy price ratio history from Reflector Oracle
+/// Returns an empty list for TWAP sources or when the oracle has no history,
+/// and an error if a record is too large to compute its ratio
+///
+/// The number of records is bounded by the sell oracle's retention period and
+/// MAX_HISTORY_RECORDS. Spot ratios only use records where both assets were
+/// quoted at the same timestamp.
+///
+/// @param env: Contract environment
+/// @param sell_feed: Where the asset being sold is priced
+/// @param buy_feed: Where the asset being bought is priced
+/// @param source: Which oracle price to use (only spot sources have a history)
+pub fn get_price_ratio_history(
+    env: &Env,
+    sell_feed: &PriceFeed,
+    buy_feed: &PriceFeed,
+    source: &PriceSource,
+) -> Result<Vec<PriceData>, ContractError> {
+    let sell_oracle = ReflectorClient::new(env, &sell_feed.oracle);
+    let buy_oracle = ReflectorClient::new(env, &buy_feed.oracle);
+    let mut history = Vec::new(env);
+
+    // Records retained by the oracle, one per resolution period
+    let resolution = (sell_oracle.resolution() as u64).max(1);
+    let records = match sell_oracle.period() {
+        Some(period) => (period / resolution).min(MAX_HISTORY_RECORDS as u64) as u32,
+        None => MAX_HISTORY_RECORDS,
+    };
+    if records == 0 {
+        return Ok(history);
+    }
+
+    match source {
+        PriceSource::Spot => {
+            let (sell_scale, buy_scale) = oracle_scales(env, sell_feed, buy_feed)?;
+            let sell_prices = sell_oracle
+                .prices(&sell_feed.asset, &records)
+                .unwrap_or(Vec::new(env));
+            let buy_prices = buy_oracle
+                .prices(&buy_feed.asset, &records)
+                .unwrap_or(Vec::new(env));
+            for sell_price in sell_prices.iter() {
+                let buy_price = buy_prices
+                    .iter()
+                    .find(|buy_price| buy_price.timestamp == sell_price.timestamp);
+                if let Some(buy_price) = buy_price {
+                    if sell_price.price > 0 && buy_price.price > 0 {
+                        history.push_back(PriceData {
+                            price: math::mul_div_floor(
+                                math::mul(sell_price.price, buy_scale)?,
+                                PRICE_SCALE,
+                                math::mul(buy_price.price, sell_scale)?,
+                            )?,
+                            timestamp: sell_price.timestamp,
+                        });
+                    }
+                }
+            }
+        }
+        PriceSource::CrossSpot => {
+            if shared_oracle(sell_feed, buy_feed).is_none() {
+                return Ok(history);
+            }
+            let cross_scale = math::pow10(sell_oracle.decimals())?;
+            let cross_prices = sell_oracle
+                .x_prices(&sell_feed.asset, &buy_feed.asset, &records)
+                .unwrap_or(Vec::new(env));
+            for cross_price in cross_prices.iter() {
+                if cross_price.price > 0 {
+                    history.push_back(PriceData {
+                        price: math::mul_div_floor(cross_price.price, PRICE_SCALE, cross_scale)?,
+                        timestamp: cross_price.timestamp,
+                    });
+                }
+            }
+        }
+        PriceSource::Twap(_) | PriceSource::CrossTwap(_) => {}
+    }
+
+    Ok(history)
 }
 
 /// Check if target price condition is met using Reflector Oracle
-/// Returns (condition_met, current_price_ratio)
+/// Returns (condition_met, current_price_ratio, price_timestamp)
 ///
 /// This function:
-/// 1. Fetches USD prices for both assets from Reflector
+/// 1. Fetches prices for both assets (or the cross price) from Reflector
 /// 2. Calculates the price ratio (sell_asset / buy_asset)
-/// 3. Compares with trigger price
+/// 3. Compares with trigger price in the trigger direction; stale prices never
+///    meet the condition
 ///
 /// @param env: Contract environment
-/// @param oracle_address: Reflector oracle contract address
-/// @param sell_asset: Asset being sold
-/// @param buy_asset: Asset being bought
-/// @param trigger_price: Target price ratio (scaled by decimals)
-/// @param use_twap: Use TWAP instead of last price for stability
+/// @param sell_feed: Where the asset being sold is priced
+/// @param buy_feed: Where the asset being bought is priced
+/// @param trigger_price: Target price ratio (scaled by PRICE_SCALE)
+/// @param direction: Trigger above (take-profit) or below (stop-loss) the target
+/// @param source: Which oracle price to use
+/// @param max_age: Maximum accepted price age, in seconds
 pub fn check_price_trigger(
     env: &Env,
-    oracle_address: &Address,
-    sell_asset: &Asset,
-    buy_asset: &Asset,
+    sell_feed: &PriceFeed,
+    buy_feed: &PriceFeed,
     trigger_price: i128,
-    use_twap: bool,
-) -> (bool, i128) {
-    let reflector = ReflectorClient::new(env, oracle_address);
-
-    // Get oracle decimals for proper scaling
-    let decimals = reflector.decimals();
+    direction: &TriggerDirection,
+    source: &PriceSource,
+    max_age: u64,
+) -> Result<(bool, i128, u64), ContractError> {
+    match get_price_ratio(env, sell_feed, buy_feed, source)? {
+        // Check if price condition is met
+        Some(price_ratio) => {
+            let condition_met = direction.is_met(price_ratio.price, trigger_price)
+                && !is_price_stale(env, price_ratio.timestamp, max_age);
+            Ok((condition_met, price_ratio.price, price_ratio.timestamp))
+        }
+        // Handle missing prices
+        None => Ok((false, 0, 0)),
+    }
+}
 
-    // Fetch prices based on preference (TWAP for stability, lastprice for spot)
-    let sell_price = if use_twap {
-        reflector.twap(&sell_asset.clone(), &5) // 5 periods for TWAP
-    } else {
-        reflector.lastprice(&sell_asset.clone()).map(|pd| pd.price)
-    };
+/// Fetch the sell/buy price ratio (see `get_price_ratio`), failing if the
+/// oracle has no price for the pair or if it is older than `max_age` seconds
+pub fn get_fresh_price_ratio(
+    env: &Env,
+    sell_feed: &PriceFeed,
+    buy_feed: &PriceFeed,
+    source: &PriceSource,
+    max_age: u64,
+) -> Result<PriceData, ContractError> {
+    let price_ratio = get_price_ratio(env, sell_feed, buy_feed, source)?
+        .ok_or(ContractError::OraclePriceUnavailable)?;
+    if is_price_stale(env, price_ratio.timestamp, max_age) {
+        return Err(ContractError::StalePrice);
+    }
+    Ok(price_ratio)
+}
 
-    let buy_price = if use_twap {
-        reflector.twap(&buy_asset.clone(), &5)
-    } else {
-        reflector.lastprice(&buy_asset.clone()).map(|pd| pd.price)
-    };
+/// Oracle of a pair priced on a single oracle (required for cross prices)
+fn shared_oracle<'a>(sell_feed: &'a PriceFeed, buy_feed: &PriceFeed) -> Option<&'a Address> {
+    (sell_feed.oracle == buy_feed.oracle).then_some(&sell_feed.oracle)
+}
 
-    // Handle missing prices
-    if sell_price.is_none() || buy_price.is_none() {
-        return (false, 0);
+/// Decimal scales (sell, buy) bringing prices from two oracles to a common precision
+/// The sell price is multiplied by the buy scale and the buy price by the sell scale;
+/// only the difference in decimals is applied, so one of them is always 1
+fn oracle_scales(
+    env: &Env,
+    sell_feed: &PriceFeed,
+    buy_feed: &PriceFeed,
+) -> Result<(i128, i128), ContractError> {
+    if sell_feed.oracle == buy_feed.oracle {
+        return Ok((1, 1));
     }
 
-    let sell_price_value = sell_price.unwrap();
-    let buy_price_value = buy_price.unwrap();
-
-    // Calculate price ratio: sell_asset / buy_asset
-    // Both prices are in USD terms, so ratio gives us the exchange rate
-    // Example: If XLM = $0.12 and USDC = $1.00, then XLM/USDC = 0.12
-    let scale = 10_i128.pow(decimals);
-    let price_ratio = (sell_price_value * scale) / buy_price_value;
+    let sell_decimals = ReflectorClient::new(env, &sell_feed.oracle).decimals();
+    let buy_decimals = ReflectorClient::new(env, &buy_feed.oracle).decimals();
+    if buy_decimals >= sell_decimals {
+        Ok((1, math::pow10(buy_decimals - sell_decimals)?))
+    } else {
+        Ok((math::pow10(sell_decimals - buy_decimals)?, 1))
+    }
+}
 
-    // Check if price condition is met
-    let condition_met = price_ratio >= trigger_price;
+/// Check whether a price recorded at `timestamp` is older than `max_age` seconds
+pub fn is_price_stale(env: &Env, timestamp: u64, max_age: u64) -> bool {
+    env.ledger().timestamp().saturating_sub(timestamp) > max_age
+}
 
-    (condition_met, price_ratio)
+/// Deviation between two prices in basis points, relative to `reference`
+pub fn deviation_bps(price: i128, reference: i128) -> Result<i128, ContractError> {
+    let difference = math::sub(price, reference)?;
+    let difference = difference.checked_abs().ok_or(ContractError::MathOverflow)?;
+    math::mul_div_floor(difference, BPS_DENOMINATOR, reference)
 }
 
 /// Get cross-rate directly from oracle using x_last_price
@@ -168,12 +378,17 @@ pub fn get_cross_rate_twap(
     reflector.x_twap(&sell_asset.clone(), &buy_asset.clone(), &records)
 }
 
+/// Check that a price source's TWAP window is within 1..=MAX_TWAP_RECORDS
+pub fn is_valid_price_source(source: &PriceSource) -> bool {
+    match source {
+        PriceSource::Spot | PriceSource::CrossSpot => true,
+        PriceSource::Twap(records) | PriceSource::CrossTwap(records) => {
+            *records > 0 && *records <= MAX_TWAP_RECORDS
+        }
+    }
+}
+
 /// Convert token address to Oracle Asset type
 pub fn stellar_asset(address: Address) -> Asset {
     Asset::Stellar(address)
 }
-
-/// Convert symbol to Oracle Asset type
-pub fn symbol_asset(symbol: Symbol) -> Asset {
-    Asset::Other(symbol)
-}
@@ -1,15 +1,24 @@
-use soroban_sdk::{Address, Env, Vec, contractclient};
+use soroban_sdk::{
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contractclient, vec, Address, Env, IntoVal, Symbol, Vec,
+};
+
+use crate::types::MAX_PATH_LENGTH;
 
 // Soroswap Router Interface
 // Based on: https://github.com/soroswap/core/tree/main/contracts/router
 // Testnet Router: CCMAPXWVZD4USEKDWRYS7DA4Y3D7E2SDMGBFJUCEXTC7VN6CUBGWPFUS
 
+#[allow(dead_code)] // only used through the generated client
 #[contractclient(name = "SoroswapRouterClient")]
 pub trait SoroswapRouterTrait {
     /// Get amounts out for a swap path
     /// Returns expected output amounts for each step in the path
     fn router_get_amounts_out(e: Env, amount_in: i128, path: Vec<Address>) -> Vec<i128>;
 
+    /// Get the pair contract address for two tokens
+    fn router_pair_for(e: Env, token_a: Address, token_b: Address) -> Address;
+
     /// Swap exact tokens for tokens
     /// Swaps an exact amount of input tokens for as many output tokens as possible
     fn swap_exact_tokens_for_tokens(
@@ -23,22 +32,27 @@ pub trait SoroswapRouterTrait {
 }
 
 /// Get a quote for swapping tokens through Soroswap
-/// Returns the expected output amount for a given input amount
+/// Returns the expected output amounts for each step in the path,
+/// or None if the router cannot quote the path (e.g. a pair has no pool)
 pub fn get_swap_quote(
     e: &Env,
     router_address: &Address,
     amount_in: i128,
     path: Vec<Address>,
-) -> Vec<i128> {
+) -> Option<Vec<i128>> {
     let router = SoroswapRouterClient::new(e, router_address);
 
     // Use router_get_amounts_out to get expected output amounts
     // This performs chained calculations on the pair path
-    router.router_get_amounts_out(&amount_in, &path)
+    router.try_router_get_amounts_out(&amount_in, &path).ok()?.ok()
 }
 
 /// Execute a swap through Soroswap Router
 /// Swaps exact input tokens for output tokens with minimum amount protection
+///
+/// The router pulls `amount_in` from `to` and sends the output back to `to`.
+/// When `to` is this contract, the first-hop transfer into the pair is
+/// authorized on the contract's behalf.
 pub fn execute_swap(
     e: &Env,
     router_address: &Address,
@@ -50,6 +64,10 @@ pub fn execute_swap(
 ) -> Vec<i128> {
     let router = SoroswapRouterClient::new(e, router_address);
 
+    if *to == e.current_contract_address() {
+        authorize_router_transfer(e, &router, amount_in, &path);
+    }
+
     // Execute swap_exact_tokens_for_tokens
     router.swap_exact_tokens_for_tokens(
         &amount_in,
@@ -60,6 +78,30 @@ pub fn execute_swap(
     )
 }
 
+/// Authorize the router to move `amount_in` of the first path token from this
+/// contract into the first pair of the path
+fn authorize_router_transfer(
+    e: &Env,
+    router: &SoroswapRouterClient,
+    amount_in: i128,
+    path: &Vec<Address>,
+) {
+    let token_in = path.get(0).unwrap();
+    let pair = router.router_pair_for(&token_in, &path.get(1).unwrap());
+
+    e.authorize_as_current_contract(vec![
+        e,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: token_in,
+                fn_name: Symbol::new(e, "transfer"),
+                args: (e.current_contract_address(), pair, amount_in).into_val(e),
+            },
+            sub_invocations: vec![e],
+        }),
+    ]);
+}
+
 /// Helper to build a swap path (direct swap between two tokens)
 pub fn build_swap_path(e: &Env, token_a: Address, token_b: Address) -> Vec<Address> {
     let mut path = Vec::new(e);
@@ -68,23 +110,67 @@ pub fn build_swap_path(e: &Env, token_a: Address, token_b: Address) -> Vec<Addre
     path
 }
 
-/// Check if a swap would meet the minimum output requirement
-pub fn check_swap_price(
+/// Build candidate swap paths: the direct path plus one two-hop path through
+/// each intermediate token
+pub fn build_candidate_paths(
+    e: &Env,
+    token_a: &Address,
+    token_b: &Address,
+    hop_tokens: &Vec<Address>,
+) -> Vec<Vec<Address>> {
+    let mut paths = Vec::new(e);
+    paths.push_back(build_swap_path(e, token_a.clone(), token_b.clone()));
+
+    for hop in hop_tokens.iter() {
+        if hop != *token_a && hop != *token_b {
+            paths.push_back(vec![e, token_a.clone(), hop, token_b.clone()]);
+        }
+    }
+
+    paths
+}
+
+/// Check that a swap path starts at `token_in`, ends at `token_out`
+/// and has between 2 and MAX_PATH_LENGTH tokens, none of them repeated
+pub fn is_valid_path(path: &Vec<Address>, token_in: &Address, token_out: &Address) -> bool {
+    if path.len() < 2 || path.len() > MAX_PATH_LENGTH {
+        return false;
+    }
+    if path.first().as_ref() != Some(token_in) || path.last().as_ref() != Some(token_out) {
+        return false;
+    }
+    for i in 1..path.len() {
+        for j in 0..i {
+            if path.get(j) == path.get(i) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Find the path with the highest expected output among the candidates
+/// Paths without liquidity (router call fails) are skipped
+/// Returns (best_path, expected_output)
+pub fn find_best_path(
     e: &Env,
     router_address: &Address,
-    sell_token: &Address,
-    buy_token: &Address,
-    sell_amount: i128,
-    min_buy_amount: i128,
-) -> (bool, i128) {
-    let path = build_swap_path(e, sell_token.clone(), buy_token.clone());
-    let amounts = get_swap_quote(e, router_address, sell_amount, path);
-
-    // The last element in amounts is the expected output
-    if let Some(expected_output) = amounts.last() {
-        let meets_minimum = expected_output >= min_buy_amount;
-        return (meets_minimum, expected_output);
+    amount_in: i128,
+    candidates: Vec<Vec<Address>>,
+) -> Option<(Vec<Address>, i128)> {
+    let mut best: Option<(Vec<Address>, i128)> = None;
+
+    for path in candidates.iter() {
+        let Some(amounts) = get_swap_quote(e, router_address, amount_in, path.clone()) else {
+            continue;
+        };
+        let Some(output) = amounts.last() else {
+            continue;
+        };
+        if best.as_ref().is_none_or(|(_, best_output)| output > *best_output) {
+            best = Some((path, output));
+        }
     }
 
-    (false, 0)
+    best
 }
@@ -1,11 +1,26 @@
-use soroban_sdk::{Address, Env, Symbol, symbol_short};
-use crate::types::{Balance, Intent};
+use soroban_sdk::{vec, Address, Env, IntoVal, Symbol, TryFromVal, Val, Vec, symbol_short};
+use crate::types::{
+    Balance, FeeConfig, FeeSide, IndexDirectory, IndexPage, Intent, IntentGroup, Ladder,
+    OrderBookEntry, PauseScope, TokenConfig, TtlConfig, DEFAULT_MAX_DEVIATION_BPS,
+    DEFAULT_MAX_PRICE_AGE, DEFAULT_TTL_EXTEND_TO, DEFAULT_TTL_THRESHOLD, INDEX_PAGE_SIZE,
+};
 
 // Storage keys
 const INTENT_COUNTER: Symbol = symbol_short!("COUNTER");
-const ADMIN: Symbol = symbol_short!("ADMIN");
+const GROUP_COUNTER: Symbol = symbol_short!("GRP_CNT");
+const LADDER_COUNTER: Symbol = symbol_short!("LAD_CNT");
 const ROUTER: Symbol = symbol_short!("ROUTER");
 const ORACLE: Symbol = symbol_short!("ORACLE");
+const MAX_DEV: Symbol = symbol_short!("MAX_DEV");
+const HOPS: Symbol = symbol_short!("HOPS");
+const KEEP_BPS: Symbol = symbol_short!("KEEP_BPS");
+const MAX_AGE: Symbol = symbol_short!("MAX_AGE");
+const FEE_CFG: Symbol = symbol_short!("FEE_CFG");
+const TREASURY: Symbol = symbol_short!("TREASURY");
+const PAUSE_SCP: Symbol = symbol_short!("PAUSE_SCP");
+const SCHEMA: Symbol = symbol_short!("SCHEMA");
+const TOKENS: Symbol = symbol_short!("TOKENS");
+const TTL_CFG: Symbol = symbol_short!("TTL_CFG");
 
 /// Get the next intent ID and increment counter
 pub fn get_next_intent_id(e: &Env) -> u64 {
@@ -14,43 +29,115 @@ pub fn get_next_intent_id(e: &Env) -> u64 {
     counter
 }
 
+/// Number of intent IDs allocated so far
+pub fn get_intent_count(e: &Env) -> u64 {
+    e.storage().instance().get(&INTENT_COUNTER).unwrap_or(0)
+}
+
 /// Store an intent
 pub fn set_intent(e: &Env, intent_id: u64, intent: &Intent) {
     let key = (Symbol::new(e, "INTENT"), intent_id);
     e.storage().persistent().set(&key, intent);
-    e.storage().persistent().extend_ttl(&key, 5184000, 5184000); // ~60 days
+    extend_persistent(e, &key);
+    extend_instance(e);
 }
 
 /// Get an intent
+/// Reading an open intent extends its TTL, so a long-dated intent is not archived
 pub fn get_intent(e: &Env, intent_id: u64) -> Option<Intent> {
     let key = (Symbol::new(e, "INTENT"), intent_id);
+    let intent: Option<Intent> = e.storage().persistent().get(&key);
+    if intent.as_ref().is_some_and(Intent::is_open) {
+        extend_persistent(e, &key);
+    }
+    intent
+}
+
+/// Extend the TTL of an intent, whatever its status
+/// Returns false if there is no such intent
+pub fn bump_intent(e: &Env, intent_id: u64) -> bool {
+    bump_persistent(e, &(Symbol::new(e, "INTENT"), intent_id))
+}
+
+/// Get the next intent group ID and increment counter
+pub fn get_next_group_id(e: &Env) -> u64 {
+    let counter: u64 = e.storage().instance().get(&GROUP_COUNTER).unwrap_or(0);
+    e.storage().instance().set(&GROUP_COUNTER, &(counter + 1));
+    counter
+}
+
+/// Store an intent group
+pub fn set_intent_group(e: &Env, group: &IntentGroup) {
+    let key = (Symbol::new(e, "GROUP"), group.id);
+    e.storage().persistent().set(&key, group);
+    extend_persistent(e, &key);
+}
+
+/// Get an intent group
+pub fn get_intent_group(e: &Env, group_id: u64) -> Option<IntentGroup> {
+    let key = (Symbol::new(e, "GROUP"), group_id);
     e.storage().persistent().get(&key)
 }
 
+/// Extend the TTL of an intent group
+/// Returns false if there is no such group
+pub fn bump_intent_group(e: &Env, group_id: u64) -> bool {
+    bump_persistent(e, &(Symbol::new(e, "GROUP"), group_id))
+}
+
+/// Get the next price ladder ID and increment counter
+pub fn get_next_ladder_id(e: &Env) -> u64 {
+    let counter: u64 = e.storage().instance().get(&LADDER_COUNTER).unwrap_or(0);
+    e.storage().instance().set(&LADDER_COUNTER, &(counter + 1));
+    counter
+}
+
+/// Store a price ladder
+pub fn set_ladder(e: &Env, ladder: &Ladder) {
+    let key = (Symbol::new(e, "LADDER"), ladder.id);
+    e.storage().persistent().set(&key, ladder);
+    extend_persistent(e, &key);
+}
+
+/// Get a price ladder
+/// Reading a stored ladder extends its TTL, so it can still be cancelled
+pub fn get_ladder(e: &Env, ladder_id: u64) -> Option<Ladder> {
+    let key = (Symbol::new(e, "LADDER"), ladder_id);
+    let ladder: Option<Ladder> = e.storage().persistent().get(&key);
+    if ladder.is_some() {
+        extend_persistent(e, &key);
+    }
+    ladder
+}
+
 /// Get user balance for a specific token
+/// Reading a stored balance extends its TTL
 pub fn get_balance(e: &Env, user: &Address, token: &Address) -> Balance {
     let key = (Symbol::new(e, "BALANCE"), user, token);
-    e.storage().persistent().get(&key).unwrap_or(Balance {
-        available: 0,
-        locked: 0,
-    })
+    match e.storage().persistent().get(&key) {
+        Some(balance) => {
+            extend_persistent(e, &key);
+            balance
+        }
+        None => Balance {
+            available: 0,
+            locked: 0,
+        },
+    }
 }
 
 /// Set user balance for a specific token
 pub fn set_balance(e: &Env, user: &Address, token: &Address, balance: &Balance) {
     let key = (Symbol::new(e, "BALANCE"), user, token);
     e.storage().persistent().set(&key, balance);
-    e.storage().persistent().extend_ttl(&key, 5184000, 5184000); // ~60 days
-}
-
-/// Get admin address
-pub fn get_admin(e: &Env) -> Option<Address> {
-    e.storage().instance().get(&ADMIN)
+    extend_persistent(e, &key);
+    extend_instance(e);
 }
 
-/// Set admin address
-pub fn set_admin(e: &Env, admin: &Address) {
-    e.storage().instance().set(&ADMIN, admin);
+/// Extend the TTL of a user's balance of a token
+/// Returns false if the user never held the token in the vault
+pub fn bump_balance(e: &Env, user: &Address, token: &Address) -> bool {
+    bump_persistent(e, &(Symbol::new(e, "BALANCE"), user, token))
 }
 
 /// Store intent ID for a user (for enumeration)
@@ -59,7 +146,7 @@ pub fn add_user_intent(e: &Env, user: &Address, intent_id: u64) {
     let mut intents: soroban_sdk::Vec<u64> = e.storage().persistent().get(&key).unwrap_or(soroban_sdk::Vec::new(e));
     intents.push_back(intent_id);
     e.storage().persistent().set(&key, &intents);
-    e.storage().persistent().extend_ttl(&key, 5184000, 5184000);
+    extend_persistent(e, &key);
 }
 
 /// Get all intent IDs for a user
@@ -68,6 +155,308 @@ pub fn get_user_intents(e: &Env, user: &Address) -> soroban_sdk::Vec<u64> {
     e.storage().persistent().get(&key).unwrap_or(soroban_sdk::Vec::new(e))
 }
 
+/// Extend the TTL of a user's intent index
+/// Returns false if the user never created an intent
+pub fn bump_user_intents(e: &Env, user: &Address) -> bool {
+    bump_persistent(e, &(Symbol::new(e, "USER_INT"), user))
+}
+
+/// Add an intent ID to the global index of active intents
+pub fn add_active_intent(e: &Env, intent_id: u64) {
+    PagedIndex::active(e).insert(e, intent_id, active_key);
+}
+
+/// Remove an intent ID from the global index of active intents
+pub fn remove_active_intent(e: &Env, intent_id: u64) {
+    PagedIndex::active(e).remove(e, intent_id as i128, |id| *id == intent_id, active_key);
+}
+
+/// List active intent IDs in ascending order
+/// @param start_after: Only return IDs greater than this
+/// @param limit: Maximum number of IDs to return
+pub fn get_active_intents(e: &Env, start_after: Option<u64>, limit: u32) -> Vec<u64> {
+    let cursor = start_after.map(|cursor| (cursor as i128, cursor));
+    PagedIndex::active(e).list(e, cursor, |id, cursor| *id == cursor, active_key, limit)
+}
+
+fn active_key(intent_id: &u64) -> i128 {
+    *intent_id as i128
+}
+
+/// Insert an intent into the order book of its token pair
+/// Entries are sorted by target price; equal prices keep insertion order
+pub fn add_to_order_book(e: &Env, sell_token: &Address, buy_token: &Address, entry: OrderBookEntry) {
+    PagedIndex::order_book(e, sell_token, buy_token).insert(e, entry, book_key);
+}
+
+/// Remove an intent from the order book of its token pair
+/// @param target_price: Target price the intent is listed at
+pub fn remove_from_order_book(
+    e: &Env,
+    sell_token: &Address,
+    buy_token: &Address,
+    intent_id: u64,
+    target_price: i128,
+) {
+    PagedIndex::order_book(e, sell_token, buy_token).remove(
+        e,
+        target_price,
+        |entry| entry.intent_id == intent_id,
+        book_key,
+    );
+}
+
+/// List the order book of a token pair, sorted by target price
+/// @param start_after: Entry to resume after; if it has left the book since, listing
+/// resumes after its target price
+/// @param limit: Maximum number of entries to return
+pub fn get_order_book(
+    e: &Env,
+    sell_token: &Address,
+    buy_token: &Address,
+    start_after: Option<OrderBookEntry>,
+    limit: u32,
+) -> Vec<OrderBookEntry> {
+    let cursor = start_after.map(|cursor| (cursor.target_price, cursor.intent_id));
+    PagedIndex::order_book(e, sell_token, buy_token).list(
+        e,
+        cursor,
+        |entry, intent_id| entry.intent_id == intent_id,
+        book_key,
+        limit,
+    )
+}
+
+fn book_key(entry: &OrderBookEntry) -> i128 {
+    entry.target_price
+}
+
+/// Sorted index sharded into pages of at most INDEX_PAGE_SIZE entries, listed in sort
+/// order by a directory, so no single ledger entry grows with the number of open intents
+/// Entries are ordered by a sort key; equal keys keep insertion order
+struct PagedIndex {
+    directory_key: Vec<Val>,
+    page_prefix: Vec<Val>,
+}
+
+impl PagedIndex {
+    fn active(e: &Env) -> Self {
+        PagedIndex {
+            directory_key: vec![e, Symbol::new(e, "ACT_DIR").into_val(e)],
+            page_prefix: vec![e, Symbol::new(e, "ACT_PAGE").into_val(e)],
+        }
+    }
+
+    fn order_book(e: &Env, sell_token: &Address, buy_token: &Address) -> Self {
+        let pair = |name: &str| -> Vec<Val> {
+            vec![
+                e,
+                Symbol::new(e, name).into_val(e),
+                sell_token.into_val(e),
+                buy_token.into_val(e),
+            ]
+        };
+        PagedIndex {
+            directory_key: pair("BOOK_DIR"),
+            page_prefix: pair("BOOK_PAGE"),
+        }
+    }
+
+    fn page_key(&self, e: &Env, page: u32) -> Vec<Val> {
+        let mut key = self.page_prefix.clone();
+        key.push_back(page.into_val(e));
+        key
+    }
+
+    fn directory(&self, e: &Env) -> IndexDirectory {
+        e.storage().persistent().get(&self.directory_key).unwrap_or(IndexDirectory {
+            next_page: 0,
+            pages: Vec::new(e),
+        })
+    }
+
+    fn set_directory(&self, e: &Env, directory: &IndexDirectory) {
+        if directory.pages.is_empty() {
+            e.storage().persistent().remove(&self.directory_key);
+        } else {
+            e.storage().persistent().set(&self.directory_key, directory);
+            extend_persistent(e, &self.directory_key);
+        }
+    }
+
+    fn entries<T>(&self, e: &Env, page: u32) -> Vec<T>
+    where
+        T: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    {
+        let key = self.page_key(e, page);
+        let entries = e.storage().persistent().get(&key).unwrap_or(Vec::new(e));
+        extend_persistent(e, &key);
+        entries
+    }
+
+    fn set_entries<T>(&self, e: &Env, page: u32, entries: &Vec<T>) {
+        let key = self.page_key(e, page);
+        if entries.is_empty() {
+            e.storage().persistent().remove(&key);
+        } else {
+            e.storage().persistent().set(&key, entries);
+            extend_persistent(e, &key);
+        }
+    }
+
+    /// Insert an entry after every entry with a lower or equal key
+    /// A page that overflows is split in two
+    fn insert<T>(&self, e: &Env, entry: T, sort_key: fn(&T) -> i128)
+    where
+        T: IntoVal<Env, Val> + TryFromVal<Env, Val> + Clone,
+    {
+        let key = sort_key(&entry);
+        let mut directory = self.directory(e);
+        if directory.pages.is_empty() {
+            let page = directory.next_page;
+            directory.next_page += 1;
+            self.set_entries(e, page, &vec![e, entry]);
+            directory.pages.push_back(IndexPage { page, last_key: key });
+            self.set_directory(e, &directory);
+            return;
+        }
+
+        // The first page ending after the key, or the last page
+        let last = directory.pages.len() - 1;
+        let index = partition_point(directory.pages.len(), |i| {
+            directory.pages.get_unchecked(i).last_key <= key
+        })
+        .min(last);
+        let mut header = directory.pages.get_unchecked(index);
+        let mut entries: Vec<T> = self.entries(e, header.page);
+        let position = partition_point(entries.len(), |i| {
+            sort_key(&entries.get_unchecked(i)) <= key
+        });
+        entries.insert(position, entry);
+
+        if entries.len() > INDEX_PAGE_SIZE {
+            let half = entries.len() / 2;
+            let upper = entries.slice(half..);
+            let lower = entries.slice(..half);
+            let upper_header = IndexPage {
+                page: directory.next_page,
+                last_key: sort_key(&upper.last_unchecked()),
+            };
+            directory.next_page += 1;
+            self.set_entries(e, upper_header.page, &upper);
+            directory.pages.insert(index + 1, upper_header);
+            entries = lower;
+        }
+
+        header.last_key = sort_key(&entries.last_unchecked());
+        self.set_entries(e, header.page, &entries);
+        directory.pages.set(index, header);
+        self.set_directory(e, &directory);
+    }
+
+    /// Remove the first entry with `key` that `matches`; empty pages are dropped
+    fn remove<T>(&self, e: &Env, key: i128, matches: impl Fn(&T) -> bool, sort_key: fn(&T) -> i128)
+    where
+        T: IntoVal<Env, Val> + TryFromVal<Env, Val> + Clone,
+    {
+        let mut directory = self.directory(e);
+        let mut index = partition_point(directory.pages.len(), |i| {
+            directory.pages.get_unchecked(i).last_key < key
+        });
+
+        // Entries with the same key can span several pages
+        while index < directory.pages.len() {
+            let mut header = directory.pages.get_unchecked(index);
+            let mut entries: Vec<T> = self.entries(e, header.page);
+            if let Some(position) = entries.iter().position(|entry| matches(&entry)) {
+                entries.remove(position as u32);
+                self.set_entries(e, header.page, &entries);
+                match entries.last() {
+                    Some(last) => {
+                        header.last_key = sort_key(&last);
+                        directory.pages.set(index, header);
+                    }
+                    None => {
+                        directory.pages.remove(index);
+                    }
+                }
+                self.set_directory(e, &directory);
+                return;
+            }
+            if header.last_key > key {
+                return;
+            }
+            index += 1;
+        }
+    }
+
+    /// List up to `limit` entries in order, starting after the cursor entry (a sort
+    /// key and a value the entry `matches`)
+    /// If the cursor entry is gone, listing resumes after every entry with its key
+    fn list<T, C: Clone>(
+        &self,
+        e: &Env,
+        cursor: Option<(i128, C)>,
+        matches: impl Fn(&T, C) -> bool,
+        sort_key: fn(&T) -> i128,
+        limit: u32,
+    ) -> Vec<T>
+    where
+        T: IntoVal<Env, Val> + TryFromVal<Env, Val> + Clone,
+    {
+        let directory = self.directory(e);
+        let mut listed = Vec::new(e);
+        let mut index = match &cursor {
+            Some((key, _)) => partition_point(directory.pages.len(), |i| {
+                directory.pages.get_unchecked(i).last_key < *key
+            }),
+            None => 0,
+        };
+        let mut skipping = cursor;
+
+        while index < directory.pages.len() && listed.len() < limit {
+            let header = directory.pages.get_unchecked(index);
+            for entry in self.entries::<T>(e, header.page).iter() {
+                if let Some((key, value)) = &skipping {
+                    let entry_key = sort_key(&entry);
+                    if entry_key < *key {
+                        continue;
+                    }
+                    if entry_key == *key {
+                        if matches(&entry, value.clone()) {
+                            skipping = None;
+                        }
+                        continue;
+                    }
+                    skipping = None;
+                }
+                if listed.len() == limit {
+                    break;
+                }
+                listed.push_back(entry);
+            }
+            index += 1;
+        }
+
+        listed
+    }
+}
+
+/// Number of leading items, out of `len`, for which `before` holds (`before` must
+/// hold for a prefix of the items only)
+fn partition_point(len: u32, before: impl Fn(u32) -> bool) -> u32 {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if before(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
 /// Get Soroswap router address
 pub fn get_router(e: &Env) -> Option<Address> {
     e.storage().instance().get(&ROUTER)
@@ -87,3 +476,244 @@ pub fn get_oracle(e: &Env) -> Option<Address> {
 pub fn set_oracle(e: &Env, oracle: &Address) {
     e.storage().instance().set(&ORACLE, oracle);
 }
+
+/// Get maximum allowed oracle deviation (basis points)
+pub fn get_max_deviation_bps(e: &Env) -> u32 {
+    e.storage().instance().get(&MAX_DEV).unwrap_or(DEFAULT_MAX_DEVIATION_BPS)
+}
+
+/// Set maximum allowed oracle deviation (basis points)
+pub fn set_max_deviation_bps(e: &Env, max_deviation_bps: u32) {
+    e.storage().instance().set(&MAX_DEV, &max_deviation_bps);
+}
+
+/// Get intermediate tokens used to build multi-hop swap paths
+pub fn get_hop_tokens(e: &Env) -> Vec<Address> {
+    e.storage().instance().get(&HOPS).unwrap_or(Vec::new(e))
+}
+
+/// Set intermediate tokens used to build multi-hop swap paths
+pub fn set_hop_tokens(e: &Env, tokens: &Vec<Address>) {
+    e.storage().instance().set(&HOPS, tokens);
+}
+
+/// Get keeper bounty for sweeping expired intents (basis points of the unpaid incentive)
+pub fn get_keeper_bounty_bps(e: &Env) -> u32 {
+    e.storage().instance().get(&KEEP_BPS).unwrap_or(0)
+}
+
+/// Set keeper bounty for sweeping expired intents (basis points of the unpaid incentive)
+pub fn set_keeper_bounty_bps(e: &Env, bounty_bps: u32) {
+    e.storage().instance().set(&KEEP_BPS, &bounty_bps);
+}
+
+/// Get maximum accepted oracle price age (seconds)
+pub fn get_max_price_age(e: &Env) -> u64 {
+    e.storage().instance().get(&MAX_AGE).unwrap_or(DEFAULT_MAX_PRICE_AGE)
+}
+
+/// Set maximum accepted oracle price age (seconds)
+pub fn set_max_price_age(e: &Env, max_age: u64) {
+    e.storage().instance().set(&MAX_AGE, &max_age);
+}
+
+/// Get the default protocol fee config (no fee unless set)
+pub fn get_default_fee_config(e: &Env) -> FeeConfig {
+    e.storage().instance().get(&FEE_CFG).unwrap_or(FeeConfig {
+        fee_bps: 0,
+        side: FeeSide::Buy,
+    })
+}
+
+/// Set the default protocol fee config
+pub fn set_default_fee_config(e: &Env, config: &FeeConfig) {
+    e.storage().instance().set(&FEE_CFG, config);
+}
+
+/// Get the protocol fee config in effect for a token pair
+pub fn get_fee_config(e: &Env, sell_token: &Address, buy_token: &Address) -> FeeConfig {
+    get_pair_fee_config(e, sell_token, buy_token).unwrap_or_else(|| get_default_fee_config(e))
+}
+
+/// Get the protocol fee config overriding the default for a token pair
+/// Reading it extends its TTL, as fills of the pair depend on it
+pub fn get_pair_fee_config(e: &Env, sell_token: &Address, buy_token: &Address) -> Option<FeeConfig> {
+    let key = (Symbol::new(e, "FEE_PAIR"), sell_token, buy_token);
+    let config: Option<FeeConfig> = e.storage().persistent().get(&key);
+    if config.is_some() {
+        extend_persistent(e, &key);
+    }
+    config
+}
+
+/// Extend the TTL of a pair's fee config
+/// Returns false if the pair uses the default fee
+pub fn bump_pair_fee_config(e: &Env, sell_token: &Address, buy_token: &Address) -> bool {
+    bump_persistent(e, &(Symbol::new(e, "FEE_PAIR"), sell_token, buy_token))
+}
+
+/// Set the protocol fee config for a token pair
+pub fn set_pair_fee_config(e: &Env, sell_token: &Address, buy_token: &Address, config: &FeeConfig) {
+    let key = (Symbol::new(e, "FEE_PAIR"), sell_token, buy_token);
+    e.storage().persistent().set(&key, config);
+    extend_persistent(e, &key);
+}
+
+/// Remove the protocol fee config of a token pair (the default applies again)
+pub fn remove_pair_fee_config(e: &Env, sell_token: &Address, buy_token: &Address) {
+    let key = (Symbol::new(e, "FEE_PAIR"), sell_token, buy_token);
+    e.storage().persistent().remove(&key);
+}
+
+/// Get the treasury address receiving protocol fees
+pub fn get_treasury(e: &Env) -> Option<Address> {
+    e.storage().instance().get(&TREASURY)
+}
+
+/// Set the treasury address receiving protocol fees
+pub fn set_treasury(e: &Env, treasury: &Address) {
+    e.storage().instance().set(&TREASURY, treasury);
+}
+
+/// Get protocol fees accrued in a token and not yet claimed
+pub fn get_accrued_fees(e: &Env, token: &Address) -> i128 {
+    let key = (Symbol::new(e, "FEES"), token);
+    e.storage().persistent().get(&key).unwrap_or(0)
+}
+
+/// Set protocol fees accrued in a token and not yet claimed
+pub fn set_accrued_fees(e: &Env, token: &Address, amount: i128) {
+    let key = (Symbol::new(e, "FEES"), token);
+    e.storage().persistent().set(&key, &amount);
+    extend_persistent(e, &key);
+}
+
+/// Check whether a pause scope is paused on its own
+pub fn is_scope_paused(e: &Env, scope: &PauseScope) -> bool {
+    e.storage().instance().get(&(PAUSE_SCP, scope.clone())).unwrap_or(false)
+}
+
+/// Pause or resume a pause scope
+pub fn set_scope_paused(e: &Env, scope: &PauseScope, paused: bool) {
+    e.storage().instance().set(&(PAUSE_SCP, scope.clone()), &paused);
+}
+
+/// Get the storage schema version (0 for contracts deployed before versioning)
+pub fn get_schema_version(e: &Env) -> u32 {
+    e.storage().instance().get(&SCHEMA).unwrap_or(0)
+}
+
+/// Set the storage schema version
+pub fn set_schema_version(e: &Env, version: u32) {
+    e.storage().instance().set(&SCHEMA, &version);
+}
+
+/// Get the config of a token, listed or delisted
+/// Reading it extends its TTL, as intents in the token depend on it
+pub fn get_token_config(e: &Env, token: &Address) -> Option<TokenConfig> {
+    let key = (Symbol::new(e, "TOKEN"), token);
+    let config: Option<TokenConfig> = e.storage().persistent().get(&key);
+    if config.is_some() {
+        extend_persistent(e, &key);
+    }
+    config
+}
+
+/// List a token, or update its config
+pub fn set_token_config(e: &Env, token: &Address, config: &TokenConfig) {
+    let key = (Symbol::new(e, "TOKEN"), token);
+    e.storage().persistent().set(&key, config);
+    extend_persistent(e, &key);
+
+    let mut tokens = get_supported_tokens(e);
+    if !tokens.contains(token) {
+        tokens.push_back(token.clone());
+        e.storage().instance().set(&TOKENS, &tokens);
+    }
+}
+
+/// Delist a token
+/// Its config is kept so existing intents are still priced from the same oracle asset
+pub fn delist_token(e: &Env, token: &Address) {
+    let mut tokens = get_supported_tokens(e);
+    if let Some(index) = tokens.first_index_of(token) {
+        tokens.remove(index);
+        e.storage().instance().set(&TOKENS, &tokens);
+    }
+}
+
+/// Get every supported token
+pub fn get_supported_tokens(e: &Env) -> Vec<Address> {
+    e.storage().instance().get(&TOKENS).unwrap_or(Vec::new(e))
+}
+
+/// Check whether a token is listed
+pub fn is_supported_token(e: &Env, token: &Address) -> bool {
+    get_supported_tokens(e).contains(token)
+}
+
+/// Get the cached decimals of a token, extending their TTL
+pub fn get_token_decimals(e: &Env, token: &Address) -> Option<u32> {
+    let key = (Symbol::new(e, "DECIMALS"), token);
+    let decimals: Option<u32> = e.storage().persistent().get(&key);
+    if decimals.is_some() {
+        extend_persistent(e, &key);
+    }
+    decimals
+}
+
+/// Cache the decimals of a token
+pub fn set_token_decimals(e: &Env, token: &Address, decimals: u32) {
+    let key = (Symbol::new(e, "DECIMALS"), token);
+    e.storage().persistent().set(&key, &decimals);
+    extend_persistent(e, &key);
+}
+
+/// Extend the TTL of a token's config and cached decimals
+/// Returns the number of entries extended
+pub fn bump_token(e: &Env, token: &Address) -> u32 {
+    let config = bump_persistent(e, &(Symbol::new(e, "TOKEN"), token));
+    let decimals = bump_persistent(e, &(Symbol::new(e, "DECIMALS"), token));
+    config as u32 + decimals as u32
+}
+
+/// Get the TTL policy (defaults unless set)
+pub fn get_ttl_config(e: &Env) -> TtlConfig {
+    e.storage().instance().get(&TTL_CFG).unwrap_or(TtlConfig {
+        persistent_threshold: DEFAULT_TTL_THRESHOLD,
+        persistent_extend_to: DEFAULT_TTL_EXTEND_TO,
+        instance_threshold: DEFAULT_TTL_THRESHOLD,
+        instance_extend_to: DEFAULT_TTL_EXTEND_TO,
+    })
+}
+
+/// Set the TTL policy
+pub fn set_ttl_config(e: &Env, config: &TtlConfig) {
+    e.storage().instance().set(&TTL_CFG, config);
+}
+
+/// Extend the TTL of the contract instance, which holds the config and counters
+/// Called on every intent and balance write, so it lives as long as the contract is used
+pub fn extend_instance(e: &Env) {
+    let config = get_ttl_config(e);
+    e.storage()
+        .instance()
+        .extend_ttl(config.instance_threshold, config.instance_extend_to);
+}
+
+/// Extend the TTL of a persistent entry according to the TTL policy
+fn extend_persistent<K: IntoVal<Env, Val>>(e: &Env, key: &K) {
+    let config = get_ttl_config(e);
+    e.storage()
+        .persistent()
+        .extend_ttl(key, config.persistent_threshold, config.persistent_extend_to);
+}
+
+/// Extend the TTL of a persistent entry if it exists
+fn bump_persistent<K: IntoVal<Env, Val>>(e: &Env, key: &K) -> bool {
+    if !e.storage().persistent().has(key) {
+        return false;
+    }
+    extend_persistent(e, key);
+    true
+}
@@ -1,48 +1,311 @@
-#![cfg(test)]
+extern crate std;
 
 use crate::contract::{LimitOrderContract, LimitOrderContractClient};
-use crate::types::{IntentStatus, PRICE_SCALE};
+use crate::error::Error;
+use crate::events;
+use crate::math;
+use crate::oracle::{Asset, PriceData};
+use crate::storage;
+use crate::types::{
+    BatchMode, FeeConfig, FeeSide, IncentiveCurve, IntentParams, IntentStatus, LadderDistribution,
+    PauseScope, PriceSource, TokenConfig, TriggerDirection, TtlConfig, DEFAULT_TTL_EXTEND_TO,
+    DEFAULT_TTL_THRESHOLD, INDEX_PAGE_SIZE, MIN_TTL_EXTEND_TO, PRICE_SCALE, SCHEMA_VERSION,
+};
 use soroban_sdk::{
-    contract, contractimpl, testutils::Address as _, token, Address, Env, String, Symbol,
+    contract, contractimpl, symbol_short,
+    testutils::{
+        storage::{Instance as _, Persistent as _},
+        Address as _, Events as _, Ledger as _, MockAuth, MockAuthInvoke,
+    },
+    token,
+    token::StellarAssetClient,
+    vec, Address, Env, Event, IntoVal, Symbol, Val, Vec,
 };
 
-// Mock token contract for testing
+// Next version of the contract, built from testdata/limit-order-v2
+mod limit_order_v2 {
+    soroban_sdk::contractimport!(file = "testdata/limit_order_v2.wasm");
+}
+
+/// Decimals reported by the mock oracle (matches Reflector mainnet)
+const ORACLE_DECIMALS: u32 = 14;
+
+// Mock Reflector oracle for testing
+#[contract]
+pub struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    /// Set the price of an asset, recorded at the current ledger timestamp
+    pub fn set_price(e: Env, asset: Asset, price: i128) {
+        let timestamp = e.ledger().timestamp();
+        e.storage()
+            .instance()
+            .set(&asset, &PriceData { price, timestamp });
+        e.storage().instance().set(&symbol_short!("LAST"), &timestamp);
+
+        // Keep one history record per timestamp, newest first
+        let key = (symbol_short!("HIST"), asset);
+        let mut history: Vec<PriceData> =
+            e.storage().instance().get(&key).unwrap_or(Vec::new(&e));
+        if history.first().is_some_and(|pd| pd.timestamp == timestamp) {
+            history.pop_front();
+        }
+        history.push_front(PriceData { price, timestamp });
+        e.storage().instance().set(&key, &history);
+    }
+
+    pub fn decimals(_e: Env) -> u32 {
+        ORACLE_DECIMALS
+    }
+
+    pub fn resolution(_e: Env) -> u32 {
+        300
+    }
+
+    pub fn period(_e: Env) -> Option<u64> {
+        Some(86400)
+    }
+
+    pub fn last_timestamp(e: Env) -> u64 {
+        e.storage()
+            .instance()
+            .get(&symbol_short!("LAST"))
+            .unwrap_or(0)
+    }
+
+    pub fn lastprice(e: Env, asset: Asset) -> Option<PriceData> {
+        e.storage().instance().get(&asset)
+    }
+
+    pub fn prices(e: Env, asset: Asset, records: u32) -> Option<Vec<PriceData>> {
+        let history: Vec<PriceData> = e
+            .storage()
+            .instance()
+            .get(&(symbol_short!("HIST"), asset))?;
+        Some(history.slice(0..records.min(history.len())))
+    }
+
+    pub fn x_prices(
+        e: Env,
+        base_asset: Asset,
+        quote_asset: Asset,
+        records: u32,
+    ) -> Option<Vec<PriceData>> {
+        let base = Self::prices(e.clone(), base_asset, records)?;
+        let quote = Self::prices(e.clone(), quote_asset, records)?;
+        let mut cross = Vec::new(&e);
+        for base_price in base.iter() {
+            if let Some(quote_price) = quote.iter().find(|pd| pd.timestamp == base_price.timestamp) {
+                cross.push_back(PriceData {
+                    price: base_price.price * 10_i128.pow(ORACLE_DECIMALS) / quote_price.price,
+                    timestamp: base_price.timestamp,
+                });
+            }
+        }
+        Some(cross)
+    }
+
+    /// Set a TWAP distinct from the spot price (defaults to spot)
+    pub fn set_twap(e: Env, asset: Asset, price: i128) {
+        e.storage().temporary().set(&asset, &price);
+    }
+
+    pub fn twap(e: Env, asset: Asset, _records: u32) -> Option<i128> {
+        e.storage()
+            .temporary()
+            .get(&asset)
+            .or_else(|| Self::lastprice(e, asset).map(|pd| pd.price))
+    }
+
+    pub fn x_last_price(e: Env, base_asset: Asset, quote_asset: Asset) -> Option<PriceData> {
+        let base = Self::lastprice(e.clone(), base_asset)?;
+        let quote = Self::lastprice(e, quote_asset)?;
+        Some(PriceData {
+            price: base.price * 10_i128.pow(ORACLE_DECIMALS) / quote.price,
+            timestamp: base.timestamp.min(quote.timestamp),
+        })
+    }
+
+    pub fn x_twap(e: Env, base_asset: Asset, quote_asset: Asset, records: u32) -> Option<i128> {
+        let base = Self::twap(e.clone(), base_asset, records)?;
+        let quote = Self::twap(e, quote_asset, records)?;
+        Some(base * 10_i128.pow(ORACLE_DECIMALS) / quote)
+    }
+}
+
+// Mock token with configurable decimals (Stellar asset contracts always use 7)
 #[contract]
-pub struct TokenContract;
+pub struct MockToken;
 
 #[contractimpl]
-impl TokenContract {
-    pub fn initialize(e: Env, admin: Address, decimal: u32, name: String, symbol: String) {
-        token::Stellar::new(e.clone()).initialize(&admin, &decimal, &name, &symbol);
+impl MockToken {
+    pub fn __constructor(e: Env, decimals: u32) {
+        e.storage().instance().set(&symbol_short!("DECIMALS"), &decimals);
+    }
+
+    pub fn decimals(e: Env) -> u32 {
+        e.storage().instance().get(&symbol_short!("DECIMALS")).unwrap()
     }
 
     pub fn mint(e: Env, to: Address, amount: i128) {
-        token::Stellar::new(e.clone()).mint(&to, &amount);
+        let balance = Self::balance(e.clone(), to.clone());
+        e.storage().instance().set(&to, &(balance + amount));
     }
 
     pub fn balance(e: Env, id: Address) -> i128 {
-        token::Stellar::new(e.clone()).balance(&id)
+        e.storage().instance().get(&id).unwrap_or(0)
     }
 
     pub fn transfer(e: Env, from: Address, to: Address, amount: i128) {
-        token::Stellar::new(e.clone()).transfer(&from, &to, &amount);
+        from.require_auth();
+        let from_balance = Self::balance(e.clone(), from.clone());
+        assert!(from_balance >= amount, "insufficient balance");
+        e.storage().instance().set(&from, &(from_balance - amount));
+        Self::mint(e, to, amount);
+    }
+}
+
+// Mock Soroswap router for testing
+// Swaps at a fixed rate per token pair and holds its own liquidity
+#[contract]
+pub struct MockRouter;
+
+#[contractimpl]
+impl MockRouter {
+    /// Set the swap rate (scaled by PRICE_SCALE) from token_in to token_out
+    pub fn set_rate(e: Env, token_in: Address, token_out: Address, rate: i128) {
+        e.storage().instance().set(&(token_in, token_out), &rate);
+    }
+
+    /// Withhold `shortfall` of every swap's output while reporting the full amount
+    pub fn set_shortfall(e: Env, shortfall: i128) {
+        e.storage().instance().set(&symbol_short!("SHORT"), &shortfall);
+    }
+
+    pub fn router_pair_for(e: Env, _token_a: Address, _token_b: Address) -> Address {
+        e.current_contract_address()
+    }
+
+    pub fn router_get_amounts_out(e: Env, amount_in: i128, path: Vec<Address>) -> Vec<i128> {
+        let mut amounts = Vec::from_array(&e, [amount_in]);
+        let mut amount = amount_in;
+        for i in 1..path.len() {
+            let rate: i128 = e
+                .storage()
+                .instance()
+                .get(&(path.get(i - 1).unwrap(), path.get(i).unwrap()))
+                .expect("no pool for pair");
+            amount = amount * rate / PRICE_SCALE;
+            amounts.push_back(amount);
+        }
+        amounts
+    }
+
+    pub fn swap_exact_tokens_for_tokens(
+        e: Env,
+        amount_in: i128,
+        amount_out_min: i128,
+        path: Vec<Address>,
+        to: Address,
+        _deadline: u64,
+    ) -> Vec<i128> {
+        to.require_auth();
+
+        let amounts = Self::router_get_amounts_out(e.clone(), amount_in, path.clone());
+        let amount_out = amounts.last().unwrap();
+        assert!(amount_out >= amount_out_min, "insufficient output amount");
+
+        let shortfall: i128 = e.storage().instance().get(&symbol_short!("SHORT")).unwrap_or(0);
+        let delivered = amount_out - shortfall;
+        let router = e.current_contract_address();
+        token::Client::new(&e, &path.first().unwrap()).transfer(&to, &router, &amount_in);
+        token::Client::new(&e, &path.last().unwrap()).transfer(&router, &to, &delivered);
+
+        amounts
     }
 }
 
-fn create_token_contract<'a>(e: &Env, admin: &Address) -> (Address, token::Client<'a>) {
-    let contract_id = e.register_contract(None, TokenContract);
-    let token_client = token::Client::new(e, &contract_id);
+fn create_limit_order_contract<'a>(
+    e: &Env,
+    admin: &Address,
+) -> (
+    LimitOrderContractClient<'a>,
+    MockOracleClient<'a>,
+    MockRouterClient<'a>,
+) {
+    let router_id = e.register(MockRouter, ());
+    let oracle_id = e.register(MockOracle, ());
+    let contract_id = e.register(LimitOrderContract, (admin, &router_id, &oracle_id));
+
+    (
+        LimitOrderContractClient::new(e, &contract_id),
+        MockOracleClient::new(e, &oracle_id),
+        MockRouterClient::new(e, &router_id),
+    )
+}
+
+/// Register a token and list it on the limit order contract, without order size limits
+fn create_token_contract<'a>(
+    e: &Env,
+    client: &LimitOrderContractClient,
+    admin: &Address,
+) -> (Address, StellarAssetClient<'a>) {
+    let contract_id = e.register_stellar_asset_contract_v2(admin.clone()).address();
+    let token_client = StellarAssetClient::new(e, &contract_id);
 
-    token_client.initialize(
+    client.set_token_config(
         admin,
-        &7,
-        &String::from_str(e, "Test Token"),
-        &String::from_str(e, "TST"),
+        &contract_id,
+        &TokenConfig {
+            min_order_size: 1,
+            max_order_size: i128::MAX,
+            oracle_asset: Asset::Stellar(contract_id.clone()),
+            oracle: None,
+        },
     );
 
     (contract_id, token_client)
 }
 
+/// Set the oracle USD price of a token (price scaled by PRICE_SCALE)
+fn set_oracle_price(oracle: &MockOracleClient, token: &Address, price: i128) {
+    let oracle_price = price * 10_i128.pow(ORACLE_DECIMALS) / PRICE_SCALE;
+    oracle.set_price(&Asset::Stellar(token.clone()), &oracle_price);
+}
+
+/// Set the oracle USD TWAP of a token (price scaled by PRICE_SCALE)
+fn set_oracle_twap(oracle: &MockOracleClient, token: &Address, price: i128) {
+    let oracle_price = price * 10_i128.pow(ORACLE_DECIMALS) / PRICE_SCALE;
+    oracle.set_twap(&Asset::Stellar(token.clone()), &oracle_price);
+}
+
+fn balance_of(e: &Env, token: &Address, id: &Address) -> i128 {
+    token::Client::new(e, token).balance(id)
+}
+
+/// Events published by `contract`, in emission order
+fn contract_events(e: &Env, contract: &Address) -> Vec<(Address, Vec<Val>, Val)> {
+    let mut emitted = Vec::new(e);
+    for event in e.events().all().iter() {
+        if event.0 == *contract {
+            emitted.push_back(event);
+        }
+    }
+    emitted
+}
+
+/// Shape of an event as recorded by the test environment
+trait EmittedBy {
+    fn emitted_by(&self, e: &Env, contract: &Address) -> (Address, Vec<Val>, Val);
+}
+
+impl<T: Event> EmittedBy for T {
+    fn emitted_by(&self, e: &Env, contract: &Address) -> (Address, Vec<Val>, Val) {
+        (contract.clone(), self.topics(e), self.data(e))
+    }
+}
+
 #[test]
 fn test_deposit_and_withdraw() {
     let e = Env::default();
@@ -52,14 +315,10 @@ fn test_deposit_and_withdraw() {
     let user = Address::generate(&e);
 
     // Create limit order contract
-    let contract_id = e.register_contract(None, LimitOrderContract);
-    let client = LimitOrderContractClient::new(&e, &contract_id);
-
-    // Initialize contract
-    client.__constructor(&admin);
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
 
     // Create token and mint to user
-    let (token_id, token_client) = create_token_contract(&e, &admin);
+    let (token_id, token_client) = create_token_contract(&e, &client, &admin);
     token_client.mint(&user, &1000);
 
     // Deposit tokens
@@ -88,13 +347,11 @@ fn test_create_intent() {
     let creator = Address::generate(&e);
 
     // Create limit order contract
-    let contract_id = e.register_contract(None, LimitOrderContract);
-    let client = LimitOrderContractClient::new(&e, &contract_id);
-    client.__constructor(&admin);
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
 
     // Create tokens
-    let (sell_token_id, sell_token) = create_token_contract(&e, &admin);
-    let (buy_token_id, _buy_token) = create_token_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, _buy_token) = create_token_contract(&e, &client, &admin);
 
     // Mint and deposit tokens
     sell_token.mint(&creator, &1000);
@@ -117,6 +374,8 @@ fn test_create_intent() {
         &target_price,
         &incentive,
         &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
     );
 
     // Check intent
@@ -144,13 +403,11 @@ fn test_execute_intent() {
     let executor = Address::generate(&e);
 
     // Create limit order contract
-    let contract_id = e.register_contract(None, LimitOrderContract);
-    let client = LimitOrderContractClient::new(&e, &contract_id);
-    client.__constructor(&admin);
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
 
     // Create tokens
-    let (sell_token_id, sell_token) = create_token_contract(&e, &admin);
-    let (buy_token_id, buy_token) = create_token_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
 
     // Setup: Creator deposits sell tokens
     sell_token.mint(&creator, &1000);
@@ -175,11 +432,17 @@ fn test_execute_intent() {
         &target_price,
         &incentive,
         &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
     );
 
+    // Oracle reports 1.6 buy tokens per sell token
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
     // Executor executes the intent with 160 buy tokens (price is met: 160/100 = 1.6 > 1.5)
     let buy_amount = 160;
-    client.execute_intent(&intent_id, &executor, &buy_amount);
+    client.execute_intent(&intent_id, &executor, &sell_amount, &buy_amount);
 
     // Check intent status
     let intent = client.get_intent(&intent_id).unwrap();
@@ -189,10 +452,10 @@ fn test_execute_intent() {
 
     // Check balances
     // Creator should have received buy tokens
-    assert_eq!(buy_token.balance(&creator), 160);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 160);
 
     // Executor should have received sell tokens + incentive
-    assert_eq!(sell_token.balance(&executor), 100 + 5);
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 100 + 5);
 
     // Creator's locked balance should be released
     let balance = client.get_balance(&creator, &sell_token_id);
@@ -208,13 +471,11 @@ fn test_cancel_intent() {
     let creator = Address::generate(&e);
 
     // Create limit order contract
-    let contract_id = e.register_contract(None, LimitOrderContract);
-    let client = LimitOrderContractClient::new(&e, &contract_id);
-    client.__constructor(&admin);
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
 
     // Create tokens
-    let (sell_token_id, sell_token) = create_token_contract(&e, &admin);
-    let (buy_token_id, _buy_token) = create_token_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, _buy_token) = create_token_contract(&e, &client, &admin);
 
     // Setup
     sell_token.mint(&creator, &1000);
@@ -236,6 +497,8 @@ fn test_cancel_intent() {
         &target_price,
         &incentive,
         &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
     );
 
     // Check locked balance
@@ -264,13 +527,11 @@ fn test_get_user_intents() {
     let creator = Address::generate(&e);
 
     // Create limit order contract
-    let contract_id = e.register_contract(None, LimitOrderContract);
-    let client = LimitOrderContractClient::new(&e, &contract_id);
-    client.__constructor(&admin);
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
 
     // Create tokens
-    let (sell_token_id, sell_token) = create_token_contract(&e, &admin);
-    let (buy_token_id, _buy_token) = create_token_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, _buy_token) = create_token_contract(&e, &client, &admin);
 
     // Setup
     sell_token.mint(&creator, &10000);
@@ -288,6 +549,8 @@ fn test_get_user_intents() {
         &(150 * PRICE_SCALE / 100),
         &5,
         &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
     );
 
     let intent_id_2 = client.create_intent(
@@ -299,6 +562,8 @@ fn test_get_user_intents() {
         &(300 * PRICE_SCALE / 200),
         &10,
         &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
     );
 
     // Get user intents
@@ -309,7 +574,7 @@ fn test_get_user_intents() {
 }
 
 #[test]
-#[should_panic(expected = "Error(InsufficientBalance)")]
+#[should_panic(expected = "Error(Contract, #6)")]
 fn test_insufficient_balance() {
     let e = Env::default();
     e.mock_all_auths();
@@ -318,13 +583,11 @@ fn test_insufficient_balance() {
     let creator = Address::generate(&e);
 
     // Create limit order contract
-    let contract_id = e.register_contract(None, LimitOrderContract);
-    let client = LimitOrderContractClient::new(&e, &contract_id);
-    client.__constructor(&admin);
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
 
     // Create tokens
-    let (sell_token_id, sell_token) = create_token_contract(&e, &admin);
-    let (buy_token_id, _buy_token) = create_token_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, _buy_token) = create_token_contract(&e, &client, &admin);
 
     // Only deposit 50 tokens
     sell_token.mint(&creator, &50);
@@ -341,11 +604,13 @@ fn test_insufficient_balance() {
         &(150 * PRICE_SCALE / 100),
         &5,
         &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
     );
 }
 
 #[test]
-#[should_panic(expected = "Error(PriceConditionNotMet)")]
+#[should_panic(expected = "Error(Contract, #7)")]
 fn test_price_not_met() {
     let e = Env::default();
     e.mock_all_auths();
@@ -355,23 +620,21 @@ fn test_price_not_met() {
     let executor = Address::generate(&e);
 
     // Create limit order contract
-    let contract_id = e.register_contract(None, LimitOrderContract);
-    let client = LimitOrderContractClient::new(&e, &contract_id);
-    client.__constructor(&admin);
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
 
     // Create tokens
-    let (sell_token_id, sell_token) = create_token_contract(&e, &admin);
-    let (buy_token_id, buy_token) = create_token_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
 
     // Setup
     sell_token.mint(&creator, &1000);
     client.deposit(&sell_token_id, &1000, &creator);
     buy_token.mint(&executor, &200);
 
-    // Create intent: target price 1.5 (150/100)
+    // Create intent: target price 1.5 (150/100), with a lower minimum buy amount
     let sell_amount = 100;
-    let min_buy_amount = 150;
-    let target_price = (min_buy_amount * PRICE_SCALE) / sell_amount;
+    let min_buy_amount = 120;
+    let target_price = (150 * PRICE_SCALE) / sell_amount;
     let incentive = 5;
     let expiry = e.ledger().timestamp() + 86400;
 
@@ -384,8 +647,2834 @@ fn test_price_not_met() {
         &target_price,
         &incentive,
         &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
     );
 
     // Try to execute with only 140 buy tokens (price = 1.4 < 1.5, should fail)
-    client.execute_intent(&intent_id, &executor, &140);
+    client.execute_intent(&intent_id, &executor, &sell_amount, &140);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #7)")]
+fn test_execute_rejects_when_oracle_below_target() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &200);
+
+    // Target price 1.5
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // Oracle only reports 1.4, even though the executor claims 1.5
+    set_oracle_price(&oracle, &sell_token_id, 14 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    client.execute_intent(&intent_id, &executor, &100, &150);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #16)")]
+fn test_execute_rejects_price_deviation() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &200);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // Oracle reports 2.0 but executor only delivers 1.6 (20% below market)
+    set_oracle_price(&oracle, &sell_token_id, 2 * PRICE_SCALE);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    client.execute_intent(&intent_id, &executor, &100, &160);
+}
+
+#[test]
+fn test_max_deviation_config() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+    assert_eq!(client.get_max_deviation(), 500);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &200);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    set_oracle_price(&oracle, &sell_token_id, 2 * PRICE_SCALE);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    // Deviation of 20% is rejected by default, accepted after raising the limit
+    assert!(client.try_execute_intent(&intent_id, &executor, &100, &160).is_err());
+
+    // Only admin can change the limit, and it cannot exceed 100%
+    assert!(client.try_set_max_deviation(&creator, &2500).is_err());
+    assert!(client.try_set_max_deviation(&admin, &10_001).is_err());
+    client.set_max_deviation(&admin, &2500);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::MaxDeviationUpdated { max_deviation_bps: 2500 }.emitted_by(&e, &client.address),
+        ]
+    );
+    assert_eq!(client.get_max_deviation(), 2500);
+
+    client.execute_intent(&intent_id, &executor, &100, &160);
+    assert_eq!(
+        client.get_intent(&intent_id).unwrap().status,
+        IntentStatus::Executed
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #15)")]
+fn test_execute_requires_oracle_price() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &200);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // No oracle prices set
+    client.execute_intent(&intent_id, &executor, &100, &160);
+}
+
+#[test]
+fn test_execute_intent_via_router() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+
+    // Router has buy token liquidity and swaps at 1.6
+    buy_token.mint(&router.address, &1000);
+    router.set_rate(&sell_token_id, &buy_token_id, &(16 * PRICE_SCALE / 10));
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // Only the executor signs; the contract authorizes its own transfer to the pool
+    e.set_auths(&[]);
+    let buy_amount = client
+        .mock_auths(&[MockAuth {
+            address: &executor,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name: "execute_intent_via_router",
+                args: (intent_id, &executor, None::<Vec<Address>>).into_val(&e),
+                sub_invokes: &[],
+            },
+        }])
+        .execute_intent_via_router(&intent_id, &executor, &None);
+    assert_eq!(buy_amount, 160);
+
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.status, IntentStatus::Executed);
+    assert_eq!(intent.executor, Some(executor.clone()));
+    assert_eq!(intent.actual_buy_amount, Some(160));
+
+    // Creator receives swap output, executor only the incentive
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 160);
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 5);
+    assert_eq!(balance_of(&e, &sell_token_id, &router.address), 100);
+    assert_eq!(balance_of(&e, &buy_token_id, &executor), 0);
+
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.locked, 0);
+    assert_eq!(balance.available, 895);
+}
+
+#[test]
+fn test_execute_via_router_pays_received_amount() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let other = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&other, &500);
+    client.deposit(&buy_token_id, &500, &other);
+
+    // The router reports 160 for 100 at 1.6, but only delivers 155
+    buy_token.mint(&router.address, &1000);
+    router.set_rate(&sell_token_id, &buy_token_id, &(16 * PRICE_SCALE / 10));
+    router.set_shortfall(&5);
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // The creator gets what the contract received; other users' funds stay put
+    assert_eq!(client.execute_intent_via_router(&intent_id, &executor, &None), 155);
+    assert_eq!(client.get_intent(&intent_id).unwrap().actual_buy_amount, Some(155));
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 155);
+    assert_eq!(balance_of(&e, &buy_token_id, &client.address), 500);
+}
+
+#[test]
+fn test_execute_via_router_respects_min_buy_amount() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+
+    // Pool only gives 1.4 per sell token
+    buy_token.mint(&router.address, &1000);
+    router.set_rate(&sell_token_id, &buy_token_id, &(14 * PRICE_SCALE / 10));
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    assert!(client
+        .try_execute_intent_via_router(&intent_id, &executor, &None)
+        .is_err());
+
+    // Nothing moved
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.status, IntentStatus::Active);
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 105);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 0);
+}
+
+#[test]
+fn test_multi_hop_route() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+    let (hop_a_id, _hop_a) = create_token_contract(&e, &client, &admin);
+    let (hop_b_id, _hop_b) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&router.address, &1000);
+
+    // No direct pool. Via hop A: 2.0 * 0.8 = 1.6, via hop B: 0.5 * 3.1 = 1.55
+    router.set_rate(&sell_token_id, &hop_a_id, &(2 * PRICE_SCALE));
+    router.set_rate(&hop_a_id, &buy_token_id, &(8 * PRICE_SCALE / 10));
+    router.set_rate(&sell_token_id, &hop_b_id, &(PRICE_SCALE / 2));
+    router.set_rate(&hop_b_id, &buy_token_id, &(31 * PRICE_SCALE / 10));
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    // Without hop tokens there is no route
+    assert!(client
+        .try_get_best_route(&sell_token_id, &buy_token_id, &100)
+        .is_err());
+
+    let hop_tokens = Vec::from_array(&e, [hop_b_id.clone(), hop_a_id.clone()]);
+    client.set_hop_tokens(&admin, &hop_tokens);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::HopTokensUpdated { tokens: hop_tokens }.emitted_by(&e, &client.address),
+        ]
+    );
+    assert_eq!(client.get_price_quote(&sell_token_id, &buy_token_id, &100), 160);
+    let (best_path, best_output) = client.get_best_route(&sell_token_id, &buy_token_id, &100);
+    assert_eq!(
+        best_path,
+        Vec::from_array(
+            &e,
+            [sell_token_id.clone(), hop_a_id.clone(), buy_token_id.clone()]
+        )
+    );
+    assert_eq!(best_output, 160);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // Routes must start with sell_token and end with buy_token
+    let bad_route = Vec::from_array(&e, [hop_a_id.clone(), buy_token_id.clone()]);
+    assert!(client
+        .try_execute_intent_via_router(&intent_id, &executor, &Some(bad_route))
+        .is_err());
+
+    // Routes must not revisit a token
+    let cyclic_route = Vec::from_array(
+        &e,
+        [sell_token_id.clone(), hop_a_id.clone(), sell_token_id.clone(), buy_token_id.clone()],
+    );
+    assert_eq!(
+        client.try_execute_intent_via_router(&intent_id, &executor, &Some(cyclic_route)),
+        Err(Ok(Error::InvalidPath))
+    );
+
+    // Executor picks the slightly worse route explicitly
+    let route = Vec::from_array(
+        &e,
+        [sell_token_id.clone(), hop_b_id.clone(), buy_token_id.clone()],
+    );
+    let buy_amount = client.execute_intent_via_router(&intent_id, &executor, &Some(route));
+    assert_eq!(buy_amount, 155);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 155);
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 5);
+}
+
+#[test]
+fn test_partial_fills() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor_1 = Address::generate(&e);
+    let executor_2 = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor_1, &200);
+    buy_token.mint(&executor_2, &200);
+
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    // Sell 100 for at least 150, incentive 10
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &10,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // Pro-rata minimum for a 30 fill is 45
+    assert!(client
+        .try_execute_intent(&intent_id, &executor_1, &30, &44)
+        .is_err());
+
+    // First executor fills 30
+    client.execute_intent(&intent_id, &executor_1, &30, &48);
+
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.status, IntentStatus::PartiallyFilled);
+    assert_eq!(intent.filled_sell_amount, 30);
+    assert_eq!(intent.filled_buy_amount, 48);
+    assert_eq!(intent.actual_buy_amount, Some(48));
+    assert_eq!(balance_of(&e, &sell_token_id, &executor_1), 30 + 3);
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 110 - 33);
+
+    // Cannot overfill
+    assert!(client
+        .try_execute_intent(&intent_id, &executor_2, &71, &114)
+        .is_err());
+
+    // Second executor fills the remaining 70
+    client.execute_intent(&intent_id, &executor_2, &70, &112);
+
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.status, IntentStatus::Executed);
+    assert_eq!(intent.filled_sell_amount, 100);
+    assert_eq!(intent.filled_buy_amount, 160);
+    assert_eq!(intent.executor, Some(executor_2.clone()));
+    assert_eq!(balance_of(&e, &sell_token_id, &executor_2), 70 + 7);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 160);
+
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.locked, 0);
+    assert_eq!(balance.available, 890);
+}
+
+#[test]
+fn test_cancel_partially_filled_intent() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &200);
+
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &7,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // Fill 25: incentive share is floor(7 * 25 / 100) = 1
+    client.execute_intent(&intent_id, &executor, &25, &40);
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 26);
+
+    // Cancelling releases the unfilled 75 and the unpaid incentive 6
+    client.cancel_intent(&intent_id, &creator);
+
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.status, IntentStatus::Cancelled);
+    assert_eq!(intent.filled_sell_amount, 25);
+
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.locked, 0);
+    assert_eq!(balance.available, 1000 - 26);
+}
+
+#[test]
+fn test_events() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    buy_token.mint(&executor, &200);
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    client.deposit(&sell_token_id, &1000, &creator);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::Deposit {
+                user: creator.clone(),
+                token: sell_token_id.clone(),
+                amount: 1000,
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+
+    let target_price = 150 * PRICE_SCALE / 100;
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &target_price,
+        &10,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::IntentCreated {
+                intent_id,
+                sell_token: sell_token_id.clone(),
+                buy_token: buy_token_id.clone(),
+                creator: creator.clone(),
+                sell_amount: 100,
+                min_buy_amount: 150,
+                target_price,
+                incentive: 10,
+                expiry,
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+
+    client.execute_intent(&intent_id, &executor, &40, &64);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::IntentExecuted {
+                intent_id,
+                creator: creator.clone(),
+                executor: executor.clone(),
+                fill_amount: 40,
+                buy_amount: 64,
+                incentive: 4,
+                status: IntentStatus::PartiallyFilled,
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+
+    client.cancel_intent(&intent_id, &creator);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::IntentCancelled {
+                intent_id,
+                creator: creator.clone(),
+                refunded: 66,
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+
+    let new_oracle = Address::generate(&e);
+    client.set_oracle(&admin, &new_oracle);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::OracleUpdated { oracle: new_oracle }.emitted_by(&e, &client.address),
+        ]
+    );
+}
+
+#[test]
+fn test_active_intents_and_order_book() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+    let (other_token_id, _other_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &10000);
+    client.deposit(&sell_token_id, &10000, &creator);
+    buy_token.mint(&executor, &1000);
+
+    // Intents on two pairs with different target prices
+    let expiry = e.ledger().timestamp() + 86400;
+    let targets = [17, 15, 16, 15];
+    let mut ids = std::vec::Vec::new();
+    for target in targets {
+        ids.push(client.create_intent(
+            &creator,
+            &sell_token_id,
+            &100,
+            &buy_token_id,
+            &(target * 10),
+            &(target * PRICE_SCALE / 10),
+            &5,
+            &expiry,
+            &PriceSource::Spot,
+            &TriggerDirection::Above,
+        ));
+    }
+    let other_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &other_token_id,
+        &100,
+        &PRICE_SCALE,
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // Global index paginates by ID
+    assert_eq!(
+        client.list_active_intents(&None, &3),
+        Vec::from_array(&e, [ids[0], ids[1], ids[2]])
+    );
+    assert_eq!(
+        client.list_active_intents(&Some(ids[2]), &3),
+        Vec::from_array(&e, [ids[3], other_id])
+    );
+    assert_eq!(client.list_active_intents(&Some(other_id), &3).len(), 0);
+
+    // Order book is sorted by target price, ties in creation order
+    let book = client.get_order_book(&sell_token_id, &buy_token_id, &None, &10);
+    let book_ids: std::vec::Vec<u64> = book.iter().map(|intent| intent.id).collect();
+    assert_eq!(book_ids, [ids[1], ids[3], ids[2], ids[0]]);
+    assert_eq!(client.get_order_book(&sell_token_id, &buy_token_id, &None, &2).len(), 2);
+    assert_eq!(client.get_order_book(&sell_token_id, &other_token_id, &None, &10).len(), 1);
+
+    // Partial fills stay listed; full fills and cancels are removed
+    set_oracle_price(&oracle, &sell_token_id, 2 * PRICE_SCALE);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    client.set_max_deviation(&admin, &5000);
+
+    client.execute_intent(&ids[1], &executor, &50, &100);
+    client.execute_intent(&ids[3], &executor, &100, &200);
+    client.cancel_intent(&ids[0], &creator);
+
+    assert_eq!(
+        client.list_active_intents(&None, &10),
+        Vec::from_array(&e, [ids[1], ids[2], other_id])
+    );
+    let book = client.get_order_book(&sell_token_id, &buy_token_id, &None, &10);
+    let book_ids: std::vec::Vec<u64> = book.iter().map(|intent| intent.id).collect();
+    assert_eq!(book_ids, [ids[1], ids[2]]);
+}
+
+#[test]
+fn test_indexes_span_pages() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, _buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &100_000);
+    client.deposit(&sell_token_id, &100_000, &creator);
+
+    // More intents than fit in two index pages, with repeated target prices out of order
+    let expiry = e.ledger().timestamp() + 86400;
+    let mut intents = std::vec::Vec::new();
+    for i in 0..(INDEX_PAGE_SIZE * 2 + 10) as i128 {
+        let target = 100 + (i * 37) % 50;
+        let id = client.create_intent(
+            &creator,
+            &sell_token_id,
+            &100,
+            &buy_token_id,
+            &target,
+            &(target * PRICE_SCALE / 100),
+            &1,
+            &expiry,
+            &PriceSource::Spot,
+            &TriggerDirection::Above,
+        );
+        intents.push((target, id));
+    }
+
+    let list_active = || {
+        let mut listed = std::vec::Vec::new();
+        loop {
+            let page = client.list_active_intents(&listed.last().copied(), &50);
+            if page.is_empty() {
+                return listed;
+            }
+            listed.extend(page.iter());
+        }
+    };
+    let book_page = |cursor: Option<u64>, limit: u32| {
+        let page = client.get_order_book(&sell_token_id, &buy_token_id, &cursor, &limit);
+        page.iter()
+            .map(|intent| (intent.target_price * 100 / PRICE_SCALE, intent.id))
+            .collect::<std::vec::Vec<_>>()
+    };
+    let list_book = || {
+        let mut listed = std::vec::Vec::new();
+        loop {
+            let page = book_page(listed.last().map(|(_, id)| *id), 50);
+            if page.is_empty() {
+                return listed;
+            }
+            listed.extend(page);
+        }
+    };
+
+    // Pages are walked in order: IDs ascending, the book by price then creation order
+    let mut expected = intents.clone();
+    expected.sort();
+    let ids: std::vec::Vec<u64> = intents.iter().map(|(_, id)| *id).collect();
+    assert_eq!(list_active(), ids);
+    assert_eq!(list_book(), expected);
+
+    // Closing intents across page boundaries keeps both indexes consistent
+    let cancelled: std::vec::Vec<(i128, u64)> = intents.iter().copied().step_by(3).collect();
+    for (_, id) in cancelled.iter() {
+        client.cancel_intent(id, &creator);
+    }
+    intents.retain(|intent| !cancelled.contains(intent));
+    expected.retain(|intent| !cancelled.contains(intent));
+    let ids: std::vec::Vec<u64> = intents.iter().map(|(_, id)| *id).collect();
+    assert_eq!(list_active(), ids);
+    assert_eq!(list_book(), expected);
+
+    // A cursor that has left the book resumes after its target price
+    let (price, id) = cancelled[0];
+    let after: std::vec::Vec<(i128, u64)> =
+        expected.iter().copied().filter(|(target, _)| *target > price).take(20).collect();
+    assert_eq!(book_page(Some(id), 20), after);
+}
+
+#[test]
+fn test_sweep_expired() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let keeper = Address::generate(&e);
+
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, _buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+
+    // Keeper earns 10% of the unpaid incentive
+    client.set_keeper_bounty(&admin, &1000);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::KeeperBountyUpdated { bounty_bps: 1000 }.emitted_by(&e, &client.address),
+        ]
+    );
+
+    let now = e.ledger().timestamp();
+    let short_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &20,
+        &(now + 100),
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+    let long_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &20,
+        &(now + 1000),
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    e.ledger().with_mut(|li| li.timestamp = now + 500);
+
+    // Expired intents are reported as such before being swept
+    assert_eq!(client.get_intent(&short_id).unwrap().status, IntentStatus::Expired);
+    assert_eq!(client.get_intent(&long_id).unwrap().status, IntentStatus::Active);
+
+    // Unknown and not-yet-expired IDs are skipped
+    let swept = client.sweep_expired(&keeper, &Vec::from_array(&e, [short_id, long_id, 99]));
+    assert_eq!(swept, 1);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::IntentExpired {
+                intent_id: short_id,
+                creator: creator.clone(),
+                keeper: keeper.clone(),
+                refunded: 118,
+                bounty: 2,
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+
+    assert_eq!(balance_of(&e, &sell_token_id, &keeper), 2);
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.locked, 120);
+    assert_eq!(balance.available, 1000 - 120 - 2);
+    assert_eq!(client.list_active_intents(&None, &10), Vec::from_array(&e, [long_id]));
+
+    // Sweeping again is a no-op
+    assert_eq!(client.sweep_expired(&keeper, &Vec::from_array(&e, [short_id])), 0);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #4)")]
+fn test_cancel_swept_intent() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let keeper = Address::generate(&e);
+
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, _buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+
+    let now = e.ledger().timestamp();
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &(now + 100),
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    e.ledger().with_mut(|li| li.timestamp = now + 500);
+    client.sweep_expired(&keeper, &Vec::from_array(&e, [intent_id]));
+
+    // No bounty configured: everything refunded
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.available, 1000);
+    assert_eq!(balance.locked, 0);
+
+    client.cancel_intent(&intent_id, &creator);
+}
+
+#[test]
+fn test_price_sources() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &1000);
+
+    // Spot has spiked to 1.6 but the TWAP is still 1.4
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    set_oracle_twap(&oracle, &sell_token_id, 14 * PRICE_SCALE / 10);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let target_price = 150 * PRICE_SCALE / 100;
+
+    // TWAP windows must be within 1..=MAX_TWAP_RECORDS
+    assert!(client
+        .try_create_intent(
+            &creator,
+            &sell_token_id,
+            &100,
+            &buy_token_id,
+            &150,
+            &target_price,
+            &5,
+            &expiry,
+            &PriceSource::Twap(0),
+            &TriggerDirection::Above,
+        )
+        .is_err());
+
+    let mut ids = std::vec::Vec::new();
+    for source in [
+        PriceSource::Spot,
+        PriceSource::Twap(5),
+        PriceSource::CrossSpot,
+        PriceSource::CrossTwap(5),
+    ] {
+        ids.push(client.create_intent(
+            &creator,
+            &sell_token_id,
+            &100,
+            &buy_token_id,
+            &150,
+            &target_price,
+            &5,
+            &expiry,
+            &source,
+            &TriggerDirection::Above,
+        ));
+    }
+    assert_eq!(client.get_intent(&ids[1]).unwrap().price_source, PriceSource::Twap(5));
+
+    // Checks and execution use each intent's own source
+    assert_eq!(client.check_intent_executable(&ids[0]), (true, 160, 0));
+    assert_eq!(client.check_intent_executable(&ids[1]), (false, 140, 0));
+    assert_eq!(client.check_intent_executable(&ids[2]), (true, 160, 0));
+    assert_eq!(client.check_intent_executable(&ids[3]), (false, 140, 0));
+
+    client.execute_intent(&ids[0], &executor, &100, &160);
+    client.execute_intent(&ids[2], &executor, &100, &160);
+    assert!(client
+        .try_execute_intent(&ids[1], &executor, &100, &160)
+        .is_err());
+    assert!(client
+        .try_execute_intent(&ids[3], &executor, &100, &160)
+        .is_err());
+
+    // Once the TWAP catches up, TWAP intents execute too
+    set_oracle_twap(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    client.execute_intent(&ids[1], &executor, &100, &160);
+    client.execute_intent(&ids[3], &executor, &100, &160);
+    assert_eq!(
+        client.get_intent(&ids[3]).unwrap().status,
+        IntentStatus::Executed
+    );
+}
+
+#[test]
+fn test_stale_oracle_price() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+    assert_eq!(client.get_max_price_age(), 900);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &1000);
+
+    let now = 10_000;
+    e.ledger().with_mut(|li| li.timestamp = now);
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    let mut ids = std::vec::Vec::new();
+    for source in [PriceSource::Spot, PriceSource::Twap(5)] {
+        ids.push(client.create_intent(
+            &creator,
+            &sell_token_id,
+            &100,
+            &buy_token_id,
+            &150,
+            &(150 * PRICE_SCALE / 100),
+            &5,
+            &(now + 86400),
+            &source,
+            &TriggerDirection::Above,
+        ));
+    }
+
+    // Oracle stops updating for 20 minutes
+    e.ledger().with_mut(|li| li.timestamp = now + 1200);
+    assert_eq!(client.check_intent_executable(&ids[0]), (false, 160, now));
+    assert_eq!(client.check_intent_executable(&ids[1]), (false, 160, now));
+    assert_eq!(
+        client.try_execute_intent(&ids[0], &executor, &100, &160),
+        Err(Ok(Error::StalePrice))
+    );
+    assert_eq!(
+        client.try_execute_intent(&ids[1], &executor, &100, &160),
+        Err(Ok(Error::StalePrice))
+    );
+
+    // Max age must cover at least one oracle period
+    assert!(client.try_set_max_price_age(&admin, &299).is_err());
+    assert!(client.try_set_max_price_age(&creator, &3600).is_err());
+
+    // Accepting older prices makes the intents executable again
+    client.set_max_price_age(&admin, &3600);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::MaxPriceAgeUpdated { max_age: 3600 }.emitted_by(&e, &client.address),
+        ]
+    );
+    assert_eq!(client.check_intent_executable(&ids[0]), (true, 160, now));
+    client.execute_intent(&ids[0], &executor, &100, &160);
+
+    // Fresh prices work with the default age as well
+    client.set_max_price_age(&admin, &900);
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    client.execute_intent(&ids[1], &executor, &100, &160);
+}
+
+#[test]
+fn test_stop_loss() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &1000);
+
+    // Stop-loss: sell 100 once the price falls to 1.5, but never for less than 120
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &120,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Below,
+    );
+
+    // Price still above the stop
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    assert_eq!(client.check_intent_executable(&intent_id), (false, 160, 0));
+    assert_eq!(
+        client.try_execute_intent(&intent_id, &executor, &100, &160),
+        Err(Ok(Error::PriceConditionNotMet))
+    );
+
+    // Price drops through the stop
+    set_oracle_price(&oracle, &sell_token_id, 13 * PRICE_SCALE / 10);
+    assert_eq!(client.check_intent_executable(&intent_id), (true, 130, 0));
+
+    // The min_buy_amount floor still applies
+    assert_eq!(
+        client.try_execute_intent(&intent_id, &executor, &100, &119),
+        Err(Ok(Error::MinBuyAmountNotMet))
+    );
+
+    client.execute_intent(&intent_id, &executor, &100, &130);
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.status, IntentStatus::Executed);
+    assert_eq!(intent.trigger_direction, TriggerDirection::Below);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 130);
+}
+
+#[test]
+fn test_trailing_stop() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &1000);
+
+    e.ledger().set_timestamp(1_000);
+    set_oracle_price(&oracle, &sell_token_id, 2 * PRICE_SCALE);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    // Trail 10% below the high-water mark, starting at 2.0 (stop at 1.8)
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &100,
+        &(2 * PRICE_SCALE),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::TrailingStop(1_000),
+    );
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.high_water_mark, 2 * PRICE_SCALE);
+    assert_eq!(intent.created_at, 1_000);
+
+    // Trail of 0 or 100% is rejected
+    assert_eq!(
+        client.try_create_intent(
+            &creator,
+            &sell_token_id,
+            &100,
+            &buy_token_id,
+            &100,
+            &(2 * PRICE_SCALE),
+            &5,
+            &expiry,
+            &PriceSource::Spot,
+            &TriggerDirection::TrailingStop(10_000),
+        ),
+        Err(Ok(Error::InvalidPrice))
+    );
+
+    // Price peaks at 3.0 between pokes, then settles at 2.8
+    e.ledger().set_timestamp(1_300);
+    set_oracle_price(&oracle, &sell_token_id, 3 * PRICE_SCALE);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    e.ledger().set_timestamp(1_600);
+    set_oracle_price(&oracle, &sell_token_id, 28 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    // The peak is read from the oracle history: stop moves up to 2.7
+    assert_eq!(client.poke_intent(&intent_id), 3 * PRICE_SCALE);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::HighWaterMarkUpdated {
+                intent_id,
+                high_water_mark: 3 * PRICE_SCALE,
+                stop_price: 27 * PRICE_SCALE / 10,
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.high_water_mark, 3 * PRICE_SCALE);
+    assert_eq!(intent.trigger_price(), Ok(27 * PRICE_SCALE / 10));
+
+    // 2.8 is still above the stop
+    assert_eq!(client.check_intent_executable(&intent_id), (false, 280, 1_600));
+    assert_eq!(
+        client.try_execute_intent(&intent_id, &executor, &100, &280),
+        Err(Ok(Error::PriceConditionNotMet))
+    );
+
+    // 2.5 is above the original 1.8 stop but below the trailed 2.7 stop
+    e.ledger().set_timestamp(1_900);
+    set_oracle_price(&oracle, &sell_token_id, 25 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    assert_eq!(client.check_intent_executable(&intent_id), (true, 250, 1_900));
+
+    client.execute_intent(&intent_id, &executor, &100, &250);
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.status, IntentStatus::Executed);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 250);
+
+    // Only open trailing stops can be poked
+    assert_eq!(client.try_poke_intent(&intent_id), Err(Ok(Error::IntentAlreadyExecuted)));
+}
+
+#[test]
+fn test_intent_group_oco() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &1000);
+
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    // Take-profit at 2.0 and stop-loss at 1.5 on the same position
+    let expiry = e.ledger().timestamp() + 86400;
+    let take_profit = IntentParams {
+        sell_token: sell_token_id.clone(),
+        sell_amount: 100,
+        buy_token: buy_token_id.clone(),
+        min_buy_amount: 200,
+        target_price: 2 * PRICE_SCALE,
+        incentive: 5,
+        expiry,
+        price_source: PriceSource::Spot,
+        trigger_direction: TriggerDirection::Above,
+    };
+    let stop_loss = IntentParams {
+        sell_amount: 80,
+        min_buy_amount: 100,
+        target_price: 15 * PRICE_SCALE / 10,
+        incentive: 4,
+        trigger_direction: TriggerDirection::Below,
+        ..take_profit.clone()
+    };
+
+    // A group needs at least two members selling the same token
+    assert_eq!(
+        client.try_create_intent_group(&creator, &Vec::from_array(&e, [take_profit.clone()])),
+        Err(Ok(Error::InvalidGroup))
+    );
+    let other_token = IntentParams {
+        sell_token: buy_token_id.clone(),
+        buy_token: sell_token_id.clone(),
+        ..stop_loss.clone()
+    };
+    assert_eq!(
+        client.try_create_intent_group(
+            &creator,
+            &Vec::from_array(&e, [take_profit.clone(), other_token])
+        ),
+        Err(Ok(Error::InvalidToken))
+    );
+
+    // One lock of max(100 + 5, 80 + 4) backs both members
+    let group_id = client.create_intent_group(
+        &creator,
+        &Vec::from_array(&e, [take_profit.clone(), stop_loss.clone()]),
+    );
+    let group = client.get_intent_group(&group_id).unwrap();
+    assert_eq!(group.intent_ids, Vec::from_array(&e, [0, 1]));
+    assert_eq!(group.locked, 105);
+    assert_eq!(client.get_intent(&1).unwrap().group_id, Some(group_id));
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.available, 895);
+    assert_eq!(balance.locked, 105);
+
+    // The stop-loss triggers: the take-profit is cancelled and the excess lock released
+    set_oracle_price(&oracle, &sell_token_id, 14 * PRICE_SCALE / 10);
+    client.execute_intent(&1, &executor, &40, &56);
+    assert_eq!(client.get_intent(&0).unwrap().status, IntentStatus::Cancelled);
+    assert_eq!(client.get_intent(&1).unwrap().status, IntentStatus::PartiallyFilled);
+    assert_eq!(
+        client.try_execute_intent(&0, &executor, &100, &200),
+        Err(Ok(Error::IntentCancelled))
+    );
+    let group = client.get_intent_group(&group_id).unwrap();
+    assert_eq!(group.filled_intent, Some(1));
+    assert_eq!(group.locked, 0);
+
+    // 105 - 84 released; the fill then unlocks 40 + 2 incentive
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.available, 916);
+    assert_eq!(balance.locked, 42);
+
+    // The filled member now behaves like a standalone intent
+    client.cancel_intent(&1, &creator);
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.available, 958);
+    assert_eq!(balance.locked, 0);
+
+    // Cancelling a member only releases what its siblings no longer need
+    let group_id = client.create_intent_group(
+        &creator,
+        &Vec::from_array(&e, [take_profit, stop_loss]),
+    );
+    client.cancel_intent(&2, &creator);
+    assert_eq!(client.get_intent_group(&group_id).unwrap().locked, 84);
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.available, 874);
+    assert_eq!(balance.locked, 84);
+
+    client.cancel_intent(&3, &creator);
+    assert_eq!(client.get_intent_group(&group_id).unwrap().locked, 0);
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.available, 958);
+    assert_eq!(balance.locked, 0);
+}
+
+#[test]
+fn test_recurring_intent() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &1000);
+    buy_token.mint(&router.address, &1000);
+    router.set_rate(&sell_token_id, &buy_token_id, &PRICE_SCALE);
+
+    set_oracle_price(&oracle, &sell_token_id, PRICE_SCALE);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    // Sell 100 every day for 3 days, for at least 90 each, as long as the price is >= 0.9
+    let params = IntentParams {
+        sell_token: sell_token_id.clone(),
+        sell_amount: 100,
+        buy_token: buy_token_id.clone(),
+        min_buy_amount: 90,
+        target_price: 9 * PRICE_SCALE / 10,
+        incentive: 2,
+        expiry: e.ledger().timestamp() + 7 * 86400,
+        price_source: PriceSource::Spot,
+        trigger_direction: TriggerDirection::Above,
+    };
+    assert_eq!(
+        client.try_create_recurring_intent(&creator, &params, &86400, &0),
+        Err(Ok(Error::InvalidConfig))
+    );
+    // A ninth tranche would only open after the intent expires
+    assert_eq!(
+        client.try_create_recurring_intent(&creator, &params, &86400, &9),
+        Err(Ok(Error::InvalidConfig))
+    );
+    let intent_id = client.create_recurring_intent(&creator, &params, &86400, &3);
+
+    // Totals over all tranches are locked upfront
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.sell_amount, 300);
+    assert_eq!(intent.min_buy_amount, 270);
+    assert_eq!(intent.incentive, 6);
+    assert_eq!(intent.amount_per_interval, 100);
+    assert_eq!(intent.tranches_remaining, 3);
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 306);
+
+    // The first tranche is open right away, one tranche per fill
+    assert_eq!(client.check_intent_executable(&intent_id), (true, 100, 0));
+    assert_eq!(
+        client.try_execute_intent(&intent_id, &executor, &200, &200),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.execute_intent(&intent_id, &executor, &100, &100);
+
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.status, IntentStatus::PartiallyFilled);
+    assert_eq!(intent.tranches_remaining, 2);
+    assert_eq!(intent.next_tranche_at, 86400);
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 102);
+
+    // The next window is not open yet
+    assert_eq!(client.check_intent_executable(&intent_id), (false, 0, 0));
+    assert_eq!(
+        client.try_execute_intent(&intent_id, &executor, &100, &100),
+        Err(Ok(Error::TrancheNotReady))
+    );
+    assert_eq!(
+        client.try_execute_intent_via_router(&intent_id, &executor, &None),
+        Err(Ok(Error::TrancheNotReady))
+    );
+
+    // Anyone can execute the second tranche through the router once it opens
+    e.ledger().set_timestamp(86400 + 3600);
+    set_oracle_price(&oracle, &sell_token_id, PRICE_SCALE);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    assert_eq!(client.execute_intent_via_router(&intent_id, &executor, &None), 100);
+
+    // The schedule doesn't drift: the third tranche opens one interval after the second
+    // window opened, although the second tranche was executed an hour late
+    assert_eq!(client.get_intent(&intent_id).unwrap().next_tranche_at, 2 * 86400);
+    e.ledger().set_timestamp(2 * 86400);
+    set_oracle_price(&oracle, &sell_token_id, PRICE_SCALE);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    client.execute_intent(&intent_id, &executor, &100, &100);
+
+    let intent = client.get_intent(&intent_id).unwrap();
+    assert_eq!(intent.status, IntentStatus::Executed);
+    assert_eq!(intent.tranches_remaining, 0);
+    assert_eq!(intent.filled_buy_amount, 300);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 300);
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 0);
+}
+
+#[test]
+fn test_auction_incentive() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &1000);
+
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    // Incentive rises linearly from 10 to 50 over the intent's lifetime
+    let params = IntentParams {
+        sell_token: sell_token_id.clone(),
+        sell_amount: 100,
+        buy_token: buy_token_id.clone(),
+        min_buy_amount: 150,
+        target_price: 150 * PRICE_SCALE / 100,
+        incentive: 50,
+        expiry: 1000,
+        price_source: PriceSource::Spot,
+        trigger_direction: TriggerDirection::Above,
+    };
+    assert_eq!(
+        client.try_create_auction_intent(&creator, &params, &60, &IncentiveCurve::Linear),
+        Err(Ok(Error::InvalidAmount))
+    );
+    let intent_id =
+        client.create_auction_intent(&creator, &params, &10, &IncentiveCurve::Linear);
+
+    // The maximum incentive is locked
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 150);
+    assert_eq!(client.get_current_incentive(&intent_id), 10);
+
+    // Halfway: half the sell amount earns half of the current 30
+    e.ledger().set_timestamp(500);
+    assert_eq!(client.get_current_incentive(&intent_id), 30);
+    client.execute_intent(&intent_id, &executor, &50, &80);
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 50 + 15);
+
+    // The unused 10 of the fill's locked 25 goes back to the creator
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.available, 850 + 10);
+    assert_eq!(balance.locked, 75);
+
+    // At expiry the full incentive is paid
+    e.ledger().set_timestamp(1000);
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    client.execute_intent(&intent_id, &executor, &50, &80);
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 100 + 15 + 25);
+
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!(balance.available, 860);
+    assert_eq!(balance.locked, 0);
+
+    // Stepwise ramps only move at each of the N steps
+    let params = IntentParams {
+        expiry: 2000,
+        ..params
+    };
+    let intent_id =
+        client.create_auction_intent(&creator, &params, &10, &IncentiveCurve::Stepwise(4));
+    e.ledger().set_timestamp(1249);
+    assert_eq!(client.get_current_incentive(&intent_id), 10);
+    e.ledger().set_timestamp(1600);
+    assert_eq!(client.get_current_incentive(&intent_id), 30);
+    e.ledger().set_timestamp(2000);
+    assert_eq!(client.get_current_incentive(&intent_id), 50);
+}
+
+#[test]
+fn test_protocol_fee() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+    let treasury = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &20000);
+    client.deposit(&sell_token_id, &20000, &creator);
+    buy_token.mint(&executor, &40000);
+
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    // The fee is capped in code and only the fee manager can set it
+    let buy_fee = FeeConfig {
+        fee_bps: 50,
+        side: FeeSide::Buy,
+    };
+    assert_eq!(
+        client.try_set_fee_config(&admin, &FeeConfig { fee_bps: 101, side: FeeSide::Buy }),
+        Err(Ok(Error::InvalidConfig))
+    );
+    assert!(client.try_set_fee_config(&creator, &buy_fee).is_err());
+    client.set_fee_config(&admin, &buy_fee);
+    assert_eq!(client.get_fee_config(&sell_token_id, &buy_token_id), buy_fee);
+
+    // 0.5% of the buy output is kept from the creator's proceeds
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &10000,
+        &buy_token_id,
+        &15000,
+        &(150 * PRICE_SCALE / 100),
+        &0,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+    client.execute_intent(&intent_id, &executor, &10000, &16000);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 15920);
+    assert_eq!(client.get_intent(&intent_id).unwrap().filled_buy_amount, 15920);
+    assert_eq!(client.get_accrued_fees(&buy_token_id), 80);
+
+    // A per-pair override takes 1% of the sell input instead
+    let sell_fee = FeeConfig {
+        fee_bps: 100,
+        side: FeeSide::Sell,
+    };
+    client.set_pair_fee_config(&admin, &sell_token_id, &buy_token_id, &sell_fee);
+    assert_eq!(client.get_fee_config(&sell_token_id, &buy_token_id), sell_fee);
+    assert_eq!(client.get_fee_config(&buy_token_id, &sell_token_id), buy_fee);
+
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &10000,
+        &buy_token_id,
+        &15000,
+        &(150 * PRICE_SCALE / 100),
+        &0,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // The executor only receives (and swaps) 9900 sell tokens
+    client.execute_intent(&intent_id, &executor, &10000, &15840);
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 10000 + 9900);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 15920 + 15840);
+    assert_eq!(client.get_accrued_fees(&sell_token_id), 100);
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 0);
+
+    client.remove_pair_fee_config(&admin, &sell_token_id, &buy_token_id);
+    assert_eq!(client.get_fee_config(&sell_token_id, &buy_token_id), buy_fee);
+
+    // Fees can only be claimed to the treasury
+    assert_eq!(client.try_claim_fees(&sell_token_id), Err(Ok(Error::InvalidConfig)));
+    client.set_treasury(&admin, &treasury);
+    assert_eq!(client.claim_fees(&sell_token_id), 100);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::FeesClaimed {
+                token: sell_token_id.clone(),
+                treasury: treasury.clone(),
+                amount: 100,
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+    assert_eq!(client.claim_fees(&buy_token_id), 80);
+    assert_eq!(client.claim_fees(&buy_token_id), 0);
+
+    assert_eq!(balance_of(&e, &sell_token_id, &treasury), 100);
+    assert_eq!(balance_of(&e, &buy_token_id, &treasury), 80);
+    assert_eq!(balance_of(&e, &sell_token_id, &client.address), 0);
+}
+
+#[test]
+fn test_roles_and_admin_transfer() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let new_admin = Address::generate(&e);
+    let config_manager = Address::generate(&e);
+    let guardian = Address::generate(&e);
+    let fee_manager = Address::generate(&e);
+    let creator = Address::generate(&e);
+
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, _buy_token) = create_token_contract(&e, &client, &admin);
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+
+    // The admin starts with every role
+    let config_role = Symbol::new(&e, "config_manager");
+    let guardian_role = Symbol::new(&e, "guardian");
+    let fee_role = Symbol::new(&e, "fee_manager");
+    assert!(client.has_role(&admin, &config_role).is_some());
+    assert!(client.has_role(&admin, &guardian_role).is_some());
+    assert!(client.has_role(&admin, &fee_role).is_some());
+
+    client.grant_role(&admin, &config_manager, &config_role);
+    client.grant_role(&admin, &guardian, &guardian_role);
+    client.grant_role(&admin, &fee_manager, &fee_role);
+
+    // Each role only unlocks its own functions
+    let new_router = Address::generate(&e);
+    client.set_router(&config_manager, &new_router);
+    assert_eq!(client.get_router(), Some(new_router.clone()));
+    assert!(client.try_set_router(&fee_manager, &new_router).is_err());
+    assert!(client.try_set_max_deviation(&guardian, &100).is_err());
+
+    let fee = FeeConfig {
+        fee_bps: 10,
+        side: FeeSide::Sell,
+    };
+    client.set_fee_config(&fee_manager, &fee);
+    assert!(client.try_set_fee_config(&config_manager, &fee).is_err());
+    assert!(client.try_set_treasury(&guardian, &guardian).is_err());
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+    assert!(client.try_admin_cancel_intent(&intent_id, &fee_manager).is_err());
+    client.admin_cancel_intent(&intent_id, &guardian);
+    assert_eq!(client.get_intent(&intent_id).unwrap().status, IntentStatus::Cancelled);
+
+    // Two-step admin transfer: nothing changes until the new admin accepts
+    client.transfer_admin_role(&new_admin, &(e.ledger().sequence() + 1000));
+    assert_eq!(client.get_admin(), Some(admin.clone()));
+    client.accept_admin_transfer();
+    assert_eq!(client.get_admin(), Some(new_admin.clone()));
+
+    // Only the new admin manages roles
+    client.revoke_role(&new_admin, &config_manager, &config_role);
+    assert!(client.try_set_router(&config_manager, &new_router).is_err());
+    assert!(client.try_grant_role(&admin, &admin, &config_role).is_err());
+}
+
+#[test]
+fn test_pause_scopes() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let guardian = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+    client.grant_role(&admin, &guardian, &Symbol::new(&e, "guardian"));
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &500, &creator);
+    buy_token.mint(&executor, &1000);
+
+    set_oracle_price(&oracle, &sell_token_id, 2 * PRICE_SCALE);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let create = |amount: i128| {
+        client.try_create_intent(
+            &creator,
+            &sell_token_id,
+            &amount,
+            &buy_token_id,
+            &(amount * 3 / 2),
+            &(150 * PRICE_SCALE / 100),
+            &5,
+            &expiry,
+            &PriceSource::Spot,
+            &TriggerDirection::Above,
+        )
+    };
+    let intent_id = create(100).unwrap().unwrap();
+    let other_id = create(100).unwrap().unwrap();
+
+    // Only guardians can pause
+    assert!(client.try_pause_scope(&creator, &PauseScope::NewIntents).is_err());
+    assert!(client.try_pause(&creator).is_err());
+
+    // Pausing new intents leaves deposits and executions running
+    client.pause_scope(&guardian, &PauseScope::NewIntents);
+    assert!(client.is_paused(&PauseScope::NewIntents));
+    assert!(!client.is_paused(&PauseScope::Executions));
+    assert_eq!(create(100), Err(Ok(Error::ContractPaused)));
+    client.deposit(&sell_token_id, &100, &creator);
+    client.execute_intent(&intent_id, &executor, &100, &200);
+
+    // Paused executions and deposits; cancellations and withdrawals still work
+    client.pause_scope(&guardian, &PauseScope::Executions);
+    client.pause_scope(&guardian, &PauseScope::Deposits);
+    assert_eq!(
+        client.try_execute_intent(&other_id, &executor, &100, &200),
+        Err(Ok(Error::ContractPaused))
+    );
+    assert_eq!(
+        client.try_deposit(&sell_token_id, &100, &creator),
+        Err(Ok(Error::ContractPaused))
+    );
+    client.cancel_intent(&other_id, &creator);
+    client.withdraw(&sell_token_id, &100, &creator);
+    assert_eq!(client.get_balance(&creator, &sell_token_id).available, 395);
+
+    // Resuming a scope emits an event
+    client.unpause_scope(&guardian, &PauseScope::NewIntents);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::PauseScopeUpdated {
+                scope: PauseScope::NewIntents,
+                paused: false,
+                guardian: guardian.clone(),
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+    let new_id = create(100).unwrap().unwrap();
+
+    // The global pause stops every scope until lifted
+    client.unpause_scope(&guardian, &PauseScope::Executions);
+    client.unpause_scope(&guardian, &PauseScope::Deposits);
+    client.pause(&guardian);
+    assert!(client.paused());
+    assert!(client.is_paused(&PauseScope::Deposits));
+    assert_eq!(create(100), Err(Ok(Error::ContractPaused)));
+    assert_eq!(
+        client.try_execute_intent(&new_id, &executor, &100, &200),
+        Err(Ok(Error::ContractPaused))
+    );
+    client.cancel_intent(&new_id, &creator);
+
+    client.unpause(&guardian);
+    assert!(!client.paused());
+    client.deposit(&sell_token_id, &100, &creator);
+}
+
+#[test]
+fn test_upgrade_and_migrate() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+
+    // This version of the contract, as built from the crate, is upgraded in place
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, _buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &sell_token_id,
+        &100,
+        &buy_token_id,
+        &150,
+        &(150 * PRICE_SCALE / 100),
+        &5,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // Fresh deployments start at the current schema
+    assert_eq!(client.get_schema_version(), SCHEMA_VERSION);
+    assert_eq!(client.try_migrate(&0, &10), Err(Ok(Error::AlreadyMigrated)));
+
+    // Only the admin can upgrade
+    let new_wasm_hash = e.deployer().upload_contract_wasm(limit_order_v2::WASM);
+    let upgrade = MockAuth {
+        address: &creator,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "upgrade",
+            args: (new_wasm_hash.clone(),).into_val(&e),
+            sub_invokes: &[],
+        },
+    };
+    assert!(client.mock_auths(&[upgrade]).try_upgrade(&new_wasm_hash).is_err());
+
+    e.mock_all_auths();
+    client.upgrade(&new_wasm_hash);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::ContractUpgraded {
+                new_wasm_hash: new_wasm_hash.clone(),
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+
+    // The second version runs against the same storage and migrates it
+    let v2 = limit_order_v2::Client::new(&e, &client.address);
+    assert_eq!(v2.get_schema_version(), SCHEMA_VERSION);
+    assert_eq!(v2.migrate(), 1);
+    assert_eq!(v2.get_schema_version(), SCHEMA_VERSION + 1);
+
+    let record = v2.get_intent(&intent_id).unwrap();
+    let field = |name: &str| record.get(Symbol::new(&e, name)).unwrap();
+    let sell_amount: i128 = field("sell_amount").into_val(&e);
+    let creator_field: Address = field("creator").into_val(&e);
+    let migrated_at: u64 = field("migrated_at").into_val(&e);
+    assert_eq!(sell_amount, 100);
+    assert_eq!(creator_field, creator);
+    assert_eq!(migrated_at, e.ledger().timestamp());
+}
+
+#[test]
+fn test_token_allowlist() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+    let unlisted_id = e.register_stellar_asset_contract_v2(admin.clone()).address();
+    let unlisted = StellarAssetClient::new(&e, &unlisted_id);
+
+    sell_token.mint(&creator, &10_000);
+    client.deposit(&sell_token_id, &10_000, &creator);
+    buy_token.mint(&creator, &1000);
+    client.deposit(&buy_token_id, &500, &creator);
+
+    // Unlisted tokens can't be deposited; withdrawals don't check the allowlist
+    unlisted.mint(&creator, &1000);
+    assert_eq!(
+        client.try_deposit(&unlisted_id, &1000, &creator),
+        Err(Ok(Error::InvalidToken))
+    );
+    assert_eq!(
+        client.try_withdraw(&unlisted_id, &1000, &creator),
+        Err(Ok(Error::InsufficientBalance))
+    );
+
+    assert_eq!(
+        client.get_supported_tokens(),
+        Vec::from_array(&e, [sell_token_id.clone(), buy_token_id.clone()])
+    );
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let create = |sell_token: &Address, buy_token: &Address, amount: i128| {
+        client.try_create_intent(
+            &creator,
+            sell_token,
+            &amount,
+            buy_token,
+            &(amount * 3 / 2),
+            &(150 * PRICE_SCALE / 100),
+            &0,
+            &expiry,
+            &PriceSource::Spot,
+            &TriggerDirection::Above,
+        )
+    };
+
+    // Unlisted tokens can't be sold or bought
+    assert_eq!(create(&unlisted_id, &buy_token_id, 100), Err(Ok(Error::InvalidToken)));
+    assert_eq!(create(&sell_token_id, &unlisted_id, 100), Err(Ok(Error::InvalidToken)));
+
+    // Order size limits of the sell token
+    let config = TokenConfig {
+        min_order_size: 100,
+        max_order_size: 5000,
+        oracle_asset: Asset::Other(symbol_short!("EURC")),
+        oracle: None,
+    };
+    assert!(client.try_set_token_config(&creator, &sell_token_id, &config).is_err());
+    let invalid = TokenConfig {
+        min_order_size: 100,
+        max_order_size: 99,
+        oracle_asset: config.oracle_asset.clone(),
+        oracle: None,
+    };
+    assert_eq!(
+        client.try_set_token_config(&admin, &sell_token_id, &invalid),
+        Err(Ok(Error::InvalidConfig))
+    );
+
+    client.set_token_config(&admin, &sell_token_id, &config);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::TokenListed {
+                token: sell_token_id.clone(),
+                config: config.clone(),
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+    assert_eq!(client.get_token_config(&sell_token_id), Some(config));
+    assert_eq!(client.get_supported_tokens().len(), 2);
+
+    assert_eq!(create(&sell_token_id, &buy_token_id, 99), Err(Ok(Error::InvalidAmount)));
+    assert_eq!(create(&sell_token_id, &buy_token_id, 5001), Err(Ok(Error::InvalidAmount)));
+    let intent_id = create(&sell_token_id, &buy_token_id, 5000).unwrap().unwrap();
+
+    // Recurring intents: min_order_size bounds each tranche, max_order_size the total
+    let recurring = |amount: i128, tranches: u32| {
+        let params = IntentParams {
+            sell_token: sell_token_id.clone(),
+            sell_amount: amount,
+            buy_token: buy_token_id.clone(),
+            min_buy_amount: amount * 3 / 2,
+            target_price: 150 * PRICE_SCALE / 100,
+            incentive: 0,
+            expiry,
+            price_source: PriceSource::Spot,
+            trigger_direction: TriggerDirection::Above,
+        };
+        client.try_create_recurring_intent(&creator, &params, &3600, &tranches)
+    };
+    assert_eq!(recurring(99, 10), Err(Ok(Error::InvalidAmount)));
+    assert_eq!(recurring(2000, 3), Err(Ok(Error::InvalidAmount)));
+    assert!(recurring(1000, 5).is_ok());
+
+    // Delisting stops new intents but leaves existing ones alone
+    client.remove_token(&admin, &buy_token_id);
+    assert!(client.get_token_config(&buy_token_id).is_some());
+    assert_eq!(
+        client.get_supported_tokens(),
+        Vec::from_array(&e, [sell_token_id.clone()])
+    );
+    assert_eq!(create(&sell_token_id, &buy_token_id, 100), Err(Ok(Error::InvalidToken)));
+    assert_eq!(
+        client.try_remove_token(&admin, &buy_token_id),
+        Err(Ok(Error::InvalidToken))
+    );
+    client.cancel_intent(&intent_id, &creator);
+
+    // Delisted tokens can't be deposited any more, but can still be withdrawn
+    assert_eq!(
+        client.try_deposit(&buy_token_id, &500, &creator),
+        Err(Ok(Error::InvalidToken))
+    );
+    client.withdraw(&buy_token_id, &500, &creator);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 1000);
+}
+
+#[test]
+fn test_oracle_asset_mapping_and_decimals() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    // Bridged BTC with 8 decimals, quoted as "BTC" by its own oracle
+    let btc_oracle = MockOracleClient::new(&e, &e.register(MockOracle, ()));
+    let wbtc_id = e.register(MockToken, (8_u32,));
+    let wbtc = MockTokenClient::new(&e, &wbtc_id);
+    client.set_token_config(
+        &admin,
+        &wbtc_id,
+        &TokenConfig {
+            min_order_size: 1,
+            max_order_size: i128::MAX,
+            oracle_asset: Asset::Other(symbol_short!("BTC")),
+            oracle: Some(btc_oracle.address.clone()),
+        },
+    );
+
+    // 7-decimal stablecoin on the default oracle
+    let (usd_id, usd) = create_token_contract(&e, &client, &admin);
+
+    btc_oracle.set_price(
+        &Asset::Other(symbol_short!("BTC")),
+        &(50_000 * 10_i128.pow(ORACLE_DECIMALS)),
+    );
+    set_oracle_price(&oracle, &usd_id, PRICE_SCALE);
+    assert_eq!(
+        client.get_token_price(&wbtc_id).unwrap().price,
+        50_000 * 10_i128.pow(ORACLE_DECIMALS)
+    );
+    assert_eq!(client.get_token_cross_rate(&wbtc_id, &usd_id), None);
+
+    wbtc.mint(&creator, &100_000_000);
+    client.deposit(&wbtc_id, &100_000_000, &creator);
+    usd.mint(&executor, &300_000_000_000);
+
+    // Sell 0.5 BTC once it is worth at least 45,000 USD (price in whole tokens)
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        &wbtc_id,
+        &50_000_000,
+        &usd_id,
+        &220_000_000_000,
+        &(45_000 * PRICE_SCALE),
+        &100_000,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    // 0.5 BTC at 50,000 USD is 25,000 USD, in 7-decimal units
+    assert_eq!(
+        client.check_intent_executable(&intent_id),
+        (true, 250_000_000_000, 0)
+    );
+
+    // A fill at 10x the oracle price in raw units is a 10x deviation in whole tokens
+    assert_eq!(
+        client.try_execute_intent(&intent_id, &executor, &50_000_000, &2_500_000_000_000),
+        Err(Ok(Error::PriceDeviationTooHigh))
+    );
+
+    client.execute_intent(&intent_id, &executor, &50_000_000, &250_000_000_000);
+    assert_eq!(client.get_intent(&intent_id).unwrap().status, IntentStatus::Executed);
+    assert_eq!(balance_of(&e, &usd_id, &creator), 250_000_000_000);
+    assert_eq!(wbtc.balance(&executor), 50_000_000 + 100_000);
+}
+
+#[test]
+fn test_math_large_amounts() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    // Rounding direction and 256-bit intermediates
+    assert_eq!(math::mul_div_floor(7, 10, 3), Ok(23));
+    assert_eq!(math::mul_div_ceil(7, 10, 3), Ok(24));
+    assert_eq!(math::mul_div_floor(-7, 10, 3), Ok(-24));
+    assert_eq!(math::mul_div_floor(i128::MAX, i128::MAX, i128::MAX), Ok(i128::MAX));
+    assert_eq!(math::mul_div_floor(i128::MAX, 2, 1), Err(Error::MathOverflow));
+    assert_eq!(math::mul_div_ceil(1, 1, 0), Err(Error::DivisionByZero));
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+
+    // Two 18-decimal tokens
+    let mut tokens = std::vec::Vec::new();
+    for _ in 0..2 {
+        let token_id = e.register(MockToken, (18_u32,));
+        client.set_token_config(
+            &admin,
+            &token_id,
+            &TokenConfig {
+                min_order_size: 1,
+                max_order_size: i128::MAX,
+                oracle_asset: Asset::Stellar(token_id.clone()),
+                oracle: None,
+            },
+        );
+        tokens.push((token_id.clone(), MockTokenClient::new(&e, &token_id)));
+    }
+    let (sell_id, sell) = &tokens[0];
+    let (buy_id, buy) = &tokens[1];
+
+    set_oracle_price(&oracle, sell_id, PRICE_SCALE);
+    set_oracle_price(&oracle, buy_id, PRICE_SCALE);
+
+    // 100 trillion tokens: amount * PRICE_SCALE no longer fits in i128
+    let amount = 10_i128.pow(32);
+    let incentive = 10_i128.pow(30);
+    sell.mint(&creator, &(amount + incentive));
+    client.deposit(sell_id, &(amount + incentive), &creator);
+    buy.mint(&executor, &amount);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let intent_id = client.create_intent(
+        &creator,
+        sell_id,
+        &amount,
+        buy_id,
+        &(amount / 10 * 9),
+        &(PRICE_SCALE * 9 / 10),
+        &incentive,
+        &expiry,
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+    assert_eq!(client.check_intent_executable(&intent_id), (true, amount, 0));
+
+    // At 10 million buy tokens per sell token the estimate exceeds i128
+    set_oracle_price(&oracle, sell_id, PRICE_SCALE * 10_000_000);
+    assert_eq!(
+        client.try_check_intent_executable(&intent_id),
+        Err(Ok(Error::MathOverflow))
+    );
+
+    // Back at parity the whole amount fills and the incentive is paid in full
+    set_oracle_price(&oracle, sell_id, PRICE_SCALE);
+    client.execute_intent(&intent_id, &executor, &amount, &amount);
+    assert_eq!(client.get_intent(&intent_id).unwrap().status, IntentStatus::Executed);
+    assert_eq!(buy.balance(&creator), amount);
+    assert_eq!(sell.balance(&executor), amount + incentive);
+}
+
+#[test]
+fn test_ttl_management() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let keeper = Address::generate(&e);
+
+    let (client, _oracle, _router) = create_limit_order_contract(&e, &admin);
+    let (xlm_token, xlm_client) = create_token_contract(&e, &client, &admin);
+    let (usdc_token, _) = create_token_contract(&e, &client, &admin);
+
+    assert_eq!(
+        client.get_ttl_config(),
+        TtlConfig {
+            persistent_threshold: DEFAULT_TTL_THRESHOLD,
+            persistent_extend_to: DEFAULT_TTL_EXTEND_TO,
+            instance_threshold: DEFAULT_TTL_THRESHOLD,
+            instance_extend_to: DEFAULT_TTL_EXTEND_TO,
+        }
+    );
+
+    // Thresholds above their extension, or extensions outside the network's bounds, are rejected
+    let config = TtlConfig {
+        persistent_threshold: 100_000,
+        persistent_extend_to: 200_000,
+        instance_threshold: 100_000,
+        instance_extend_to: 200_000,
+    };
+    assert_eq!(
+        client.try_set_ttl_config(
+            &admin,
+            &TtlConfig {
+                persistent_threshold: 300_000,
+                ..config.clone()
+            }
+        ),
+        Err(Ok(Error::InvalidConfig))
+    );
+    assert_eq!(
+        client.try_set_ttl_config(
+            &admin,
+            &TtlConfig {
+                instance_extend_to: u32::MAX,
+                ..config.clone()
+            }
+        ),
+        Err(Ok(Error::InvalidConfig))
+    );
+    for extend_to in [0, MIN_TTL_EXTEND_TO - 1] {
+        assert_eq!(
+            client.try_set_ttl_config(
+                &admin,
+                &TtlConfig {
+                    persistent_threshold: 0,
+                    persistent_extend_to: extend_to,
+                    ..config.clone()
+                }
+            ),
+            Err(Ok(Error::InvalidConfig))
+        );
+    }
+    assert!(client.try_set_ttl_config(&keeper, &config).is_err());
+    client.set_ttl_config(&admin, &config);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::TtlConfigUpdated { config: config.clone() }.emitted_by(&e, &client.address),
+        ]
+    );
+
+    xlm_client.mint(&creator, &1000);
+    client.deposit(&xlm_token, &1000, &creator);
+    let intent_id = client.create_intent(
+        &creator,
+        &xlm_token,
+        &500,
+        &usdc_token,
+        &60,
+        &1_200_000,
+        &5,
+        &(e.ledger().timestamp() + 365 * 86400),
+        &PriceSource::Spot,
+        &TriggerDirection::Above,
+    );
+
+    let intent_key = (Symbol::new(&e, "INTENT"), intent_id);
+    let balance_key = (Symbol::new(&e, "BALANCE"), creator.clone(), xlm_token.clone());
+    let intent_ttl =
+        || e.as_contract(&client.address, || e.storage().persistent().get_ttl(&intent_key));
+    let balance_ttl =
+        || e.as_contract(&client.address, || e.storage().persistent().get_ttl(&balance_key));
+    let instance_ttl = || e.as_contract(&client.address, || e.storage().instance().get_ttl());
+
+    // Writes extend intents, balances and the instance
+    assert_eq!(intent_ttl(), 200_000);
+    assert_eq!(balance_ttl(), 200_000);
+    assert_eq!(instance_ttl(), 200_000);
+
+    // Reading an open intent extends it once it falls below the threshold
+    e.ledger().set_sequence_number(e.ledger().sequence() + 150_000);
+    assert_eq!(intent_ttl(), 50_000);
+    client.get_intent(&intent_id);
+    assert_eq!(intent_ttl(), 200_000);
+    assert_eq!(balance_ttl(), 50_000);
+
+    // Anyone can bump the rest: the intent index, the XLM balance, both token configs and
+    // cached decimals, and the instance (the creator never held USDC in the vault)
+    let tokens = Vec::from_array(&e, [xlm_token.clone(), usdc_token.clone()]);
+    assert_eq!(client.bump(&creator, &tokens, &Vec::from_array(&e, [intent_id])), 7);
+    assert_eq!(balance_ttl(), 200_000);
+    assert_eq!(instance_ttl(), 200_000);
+    assert_eq!(client.get_balance(&creator, &xlm_token).locked, 505);
+}
+
+#[test]
+fn test_update_intent() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &1000);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let create = |target_price: i128| {
+        client.create_intent(
+            &creator,
+            &sell_token_id,
+            &100,
+            &buy_token_id,
+            &120,
+            &target_price,
+            &10,
+            &expiry,
+            &PriceSource::Spot,
+            &TriggerDirection::Above,
+        )
+    };
+    let first = create(12 * PRICE_SCALE / 10);
+    let second = create(15 * PRICE_SCALE / 10);
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 220);
+
+    // Sell more for a higher incentive, at a price above the second intent's
+    client.update_intent(
+        &first,
+        &creator,
+        &(2 * PRICE_SCALE),
+        &400,
+        &20,
+        &(expiry + 86400),
+        &200,
+    );
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::IntentUpdated {
+                intent_id: first,
+                creator: creator.clone(),
+                sell_amount: 200,
+                min_buy_amount: 400,
+                target_price: 2 * PRICE_SCALE,
+                incentive: 20,
+                expiry: expiry + 86400,
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+
+    // Same ID, the extra 110 is locked and the order book is re-sorted
+    let intent = client.get_intent(&first).unwrap();
+    assert_eq!(intent.sell_amount, 200);
+    assert_eq!(intent.incentive, 20);
+    assert_eq!(intent.min_incentive, 20);
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!((balance.available, balance.locked), (670, 330));
+    let book = client.get_order_book(&sell_token_id, &buy_token_id, &None, &10);
+    assert_eq!(book.get_unchecked(0).id, second);
+    assert_eq!(book.get_unchecked(1).id, first);
+
+    // Shrinking releases the difference
+    client.update_intent(&first, &creator, &(2 * PRICE_SCALE), &100, &5, &expiry, &50);
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!((balance.available, balance.locked), (835, 165));
+
+    // Same validation as create_intent, and the lock must be covered
+    assert_eq!(
+        client.try_update_intent(&first, &creator, &(2 * PRICE_SCALE), &100, &60, &expiry, &50),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_update_intent(&first, &creator, &0, &100, &5, &expiry, &50),
+        Err(Ok(Error::InvalidPrice))
+    );
+    assert_eq!(
+        client.try_update_intent(&first, &creator, &(2 * PRICE_SCALE), &100, &5, &expiry, &900),
+        Err(Ok(Error::InsufficientBalance))
+    );
+    assert_eq!(
+        client.try_update_intent(&first, &executor, &(2 * PRICE_SCALE), &100, &5, &expiry, &50),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    // Once partially filled an intent can only be cancelled
+    set_oracle_price(&oracle, &sell_token_id, 15 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    client.execute_intent(&second, &executor, &40, &60);
+    assert_eq!(
+        client.try_update_intent(&second, &creator, &PRICE_SCALE, &100, &10, &expiry, &100),
+        Err(Ok(Error::IntentNotModifiable))
+    );
+}
+
+#[test]
+fn test_batch_operations() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &1000);
+    set_oracle_price(&oracle, &sell_token_id, 15 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let params = |sell_amount: i128, target_price: i128| IntentParams {
+        sell_token: sell_token_id.clone(),
+        sell_amount,
+        buy_token: buy_token_id.clone(),
+        min_buy_amount: sell_amount,
+        target_price,
+        incentive: 10,
+        expiry,
+        price_source: PriceSource::Spot,
+        trigger_direction: TriggerDirection::Above,
+    };
+
+    // A ladder of three limit orders in one call
+    let ladder = Vec::from_array(
+        &e,
+        [
+            params(100, PRICE_SCALE),
+            params(100, 12 * PRICE_SCALE / 10),
+            params(100, 2 * PRICE_SCALE),
+        ],
+    );
+    let created = client.create_intents(&creator, &ladder, &BatchMode::AllOrNothing);
+    assert_eq!(created, Vec::from_array(&e, [Ok(0), Ok(1), Ok(2)]));
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 330);
+
+    // One invalid intent reverts an all-or-nothing batch, and is skipped in best effort
+    let mixed = Vec::from_array(
+        &e,
+        [params(100, PRICE_SCALE), params(100, 0), params(900, PRICE_SCALE)],
+    );
+    assert_eq!(
+        client.try_create_intents(&creator, &mixed, &BatchMode::AllOrNothing),
+        Err(Ok(Error::InvalidPrice))
+    );
+    assert_eq!(client.get_user_intents(&creator).len(), 3);
+    assert_eq!(
+        client.create_intents(&creator, &mixed, &BatchMode::BestEffort),
+        Vec::from_array(&e, [Ok(3), Err(Error::InvalidPrice), Err(Error::InsufficientBalance)])
+    );
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 440);
+    assert_eq!(
+        client.try_create_intents(&creator, &Vec::new(&e), &BatchMode::BestEffort),
+        Err(Ok(Error::InvalidBatch))
+    );
+
+    // Fills go through at the oracle price (1.5); the 2.0 target has not been reached
+    let fills = Vec::from_array(&e, [(0, 100), (1, 40), (2, 100)]);
+    assert_eq!(
+        client.try_execute_intents(&executor, &fills, &BatchMode::AllOrNothing),
+        Err(Ok(Error::PriceConditionNotMet))
+    );
+    assert_eq!(client.get_intent(&0).unwrap().status, IntentStatus::Active);
+    assert_eq!(
+        client.execute_intents(&executor, &fills, &BatchMode::BestEffort),
+        Vec::from_array(&e, [Ok(150), Ok(60), Err(Error::PriceConditionNotMet)])
+    );
+    assert_eq!(client.get_intent(&0).unwrap().status, IntentStatus::Executed);
+    assert_eq!(client.get_intent(&1).unwrap().status, IntentStatus::PartiallyFilled);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 210);
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 140 + 10 + 4);
+
+    // Cancel the rest of the ladder; the executed intent is reported, not fatal
+    let ids = Vec::from_array(&e, [0, 1, 2, 3]);
+    assert_eq!(
+        client.try_cancel_intents(&creator, &ids, &BatchMode::AllOrNothing),
+        Err(Ok(Error::IntentAlreadyExecuted))
+    );
+    assert_eq!(
+        client.cancel_intents(&creator, &ids, &BatchMode::BestEffort),
+        Vec::from_array(&e, [Err(Error::IntentAlreadyExecuted), Ok(()), Ok(()), Ok(())])
+    );
+    let balance = client.get_balance(&creator, &sell_token_id);
+    assert_eq!((balance.available, balance.locked), (1000 - 140 - 14, 0));
+}
+
+#[test]
+fn test_batch_fills_at_oracle_price() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &2000);
+    set_oracle_price(&oracle, &sell_token_id, 15 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let create = |sell_amount: i128| {
+        client.create_intent(
+            &creator,
+            &sell_token_id,
+            &sell_amount,
+            &buy_token_id,
+            &(sell_amount * 3 / 2),
+            &(15 * PRICE_SCALE / 10),
+            &0,
+            &expiry,
+            &PriceSource::Spot,
+            &TriggerDirection::Above,
+        )
+    };
+    let small = create(100);
+    let large = create(500);
+
+    // A fill of 3 with the oracle right at the 1.5 target delivers 4.5, rounded up
+    let fills = Vec::from_array(&e, [(small, 3)]);
+    assert_eq!(
+        client.execute_intents(&executor, &fills, &BatchMode::AllOrNothing),
+        Vec::from_array(&e, [Ok(5)])
+    );
+
+    // With a 1% sell-side fee, only the 495 the executor receives are priced (at 1.6),
+    // so the fill is at the oracle price even with a deviation limit below the fee
+    client.set_fee_config(&admin, &FeeConfig { fee_bps: 100, side: FeeSide::Sell });
+    client.set_max_deviation(&admin, &50);
+    set_oracle_price(&oracle, &sell_token_id, 16 * PRICE_SCALE / 10);
+    let (executable, quote, _) = client.check_intent_executable(&large);
+    assert_eq!((executable, quote), (true, 792));
+    let fills = Vec::from_array(&e, [(large, 500)]);
+    assert_eq!(
+        client.execute_intents(&executor, &fills, &BatchMode::AllOrNothing),
+        Vec::from_array(&e, [Ok(792)])
+    );
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 3 + 495);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 5 + 792);
+    assert_eq!(client.get_accrued_fees(&sell_token_id), 5);
+}
+
+#[test]
+fn test_failed_batch_fill_writes_nothing() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &1000);
+    client.deposit(&sell_token_id, &1000, &creator);
+    buy_token.mint(&executor, &2000);
+    set_oracle_price(&oracle, &sell_token_id, 15 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    for _ in 0..2 {
+        client.create_intent(
+            &creator,
+            &sell_token_id,
+            &500,
+            &buy_token_id,
+            &500,
+            &PRICE_SCALE,
+            &0,
+            &expiry,
+            &PriceSource::Spot,
+            &TriggerDirection::Above,
+        );
+    }
+
+    // A 1% sell-side fee (5 per fill) with room left for a single fee accrual
+    client.set_fee_config(&admin, &FeeConfig { fee_bps: 100, side: FeeSide::Sell });
+    e.as_contract(&client.address, || {
+        storage::set_accrued_fees(&e, &sell_token_id, i128::MAX - 5);
+    });
+
+    // The second fill fails its checked math before any of its tokens move; the
+    // first delivers the 495 the executor receives at 1.5, rounded up
+    let fills = Vec::from_array(&e, [(0, 500), (1, 500)]);
+    assert_eq!(
+        client.execute_intents(&executor, &fills, &BatchMode::BestEffort),
+        Vec::from_array(&e, [Ok(743), Err(Error::MathOverflow)])
+    );
+    assert_eq!(client.get_intent(&0).unwrap().status, IntentStatus::Executed);
+    let intent = client.get_intent(&1).unwrap();
+    assert_eq!((intent.status, intent.filled_sell_amount), (IntentStatus::Active, 0));
+    assert_eq!(balance_of(&e, &sell_token_id, &executor), 495);
+    assert_eq!(balance_of(&e, &buy_token_id, &creator), 743);
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 500);
+    assert_eq!(client.get_accrued_fees(&sell_token_id), i128::MAX);
+}
+
+#[test]
+fn test_price_ladder() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let creator = Address::generate(&e);
+    let executor = Address::generate(&e);
+
+    let (client, oracle, _router) = create_limit_order_contract(&e, &admin);
+    let (sell_token_id, sell_token) = create_token_contract(&e, &client, &admin);
+    let (buy_token_id, buy_token) = create_token_contract(&e, &client, &admin);
+
+    sell_token.mint(&creator, &2000);
+    client.deposit(&sell_token_id, &2000, &creator);
+    buy_token.mint(&executor, &1000);
+
+    let expiry = e.ledger().timestamp() + 86400;
+    let ladder = |total_amount: i128, steps: u32, distribution: LadderDistribution| {
+        client.try_create_ladder(
+            &creator,
+            &sell_token_id,
+            &buy_token_id,
+            &total_amount,
+            &PRICE_SCALE,
+            &(16 * PRICE_SCALE / 10),
+            &steps,
+            &distribution,
+            &40,
+            &expiry,
+        )
+    };
+
+    // 1,000 over 4 levels from 1.0 to 1.6, sized 1:2:3:4
+    let ladder_id = ladder(1000, 4, LadderDistribution::Linear).unwrap().unwrap();
+    let intent_ids = client.get_ladder(&ladder_id).unwrap().intent_ids;
+    let levels: std::vec::Vec<(i128, i128, i128, i128)> = intent_ids
+        .iter()
+        .map(|id| {
+            let intent = client.get_intent(&id).unwrap();
+            (intent.target_price, intent.sell_amount, intent.min_buy_amount, intent.incentive)
+        })
+        .collect();
+    assert_eq!(
+        levels,
+        [
+            (PRICE_SCALE, 100, 100, 4),
+            (12 * PRICE_SCALE / 10, 200, 240, 8),
+            (14 * PRICE_SCALE / 10, 300, 420, 12),
+            (16 * PRICE_SCALE / 10, 400, 640, 16),
+        ]
+    );
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 1040);
+
+    // Geometric sizes double at every level; the last level takes the remainder
+    let geometric_id = ladder(700, 3, LadderDistribution::Geometric(20_000)).unwrap().unwrap();
+    let sizes: std::vec::Vec<i128> = client
+        .get_ladder(&geometric_id)
+        .unwrap()
+        .intent_ids
+        .iter()
+        .map(|id| client.get_intent(&id).unwrap().sell_amount)
+        .collect();
+    assert_eq!(sizes, [100, 200, 400]);
+
+    assert_eq!(ladder(1000, 1, LadderDistribution::Linear), Err(Ok(Error::InvalidConfig)));
+    assert_eq!(ladder(1000, 21, LadderDistribution::Linear), Err(Ok(Error::InvalidBatch)));
+    assert_eq!(
+        ladder(1000, 4, LadderDistribution::Geometric(0)),
+        Err(Ok(Error::InvalidConfig))
+    );
+    // A ratio of 0.01% shrinks the third level's weight (1e7 * 1e-8) to zero
+    assert_eq!(
+        ladder(1000, 3, LadderDistribution::Geometric(1)),
+        Err(Ok(Error::InvalidConfig))
+    );
+    assert_eq!(
+        ladder(5000, 4, LadderDistribution::Linear),
+        Err(Ok(Error::InsufficientBalance))
+    );
+
+    // The market reaches 1.3: the two lowest levels fill on their own
+    set_oracle_price(&oracle, &sell_token_id, 13 * PRICE_SCALE / 10);
+    set_oracle_price(&oracle, &buy_token_id, PRICE_SCALE);
+    let fills = Vec::from_array(
+        &e,
+        [(intent_ids.get_unchecked(0), 100), (intent_ids.get_unchecked(1), 200)],
+    );
+    client.execute_intents(&executor, &fills, &BatchMode::AllOrNothing);
+
+    // Only the creator can cancel the ladder; filled levels are left as they are
+    assert_eq!(
+        client.try_cancel_ladder(&ladder_id, &executor),
+        Err(Ok(Error::OnlyCreatorCanCancel))
+    );
+    assert_eq!(client.cancel_ladder(&ladder_id, &creator), 2);
+    assert_eq!(
+        contract_events(&e, &client.address),
+        vec![
+            &e,
+            events::IntentCancelled {
+                intent_id: intent_ids.get_unchecked(2),
+                creator: creator.clone(),
+                refunded: 312,
+            }
+            .emitted_by(&e, &client.address),
+            events::IntentCancelled {
+                intent_id: intent_ids.get_unchecked(3),
+                creator: creator.clone(),
+                refunded: 416,
+            }
+            .emitted_by(&e, &client.address),
+            events::LadderCancelled {
+                ladder_id,
+                creator: creator.clone(),
+                cancelled: 2,
+            }
+            .emitted_by(&e, &client.address),
+        ]
+    );
+    assert_eq!(
+        client.get_intent(&intent_ids.get_unchecked(3)).unwrap().status,
+        IntentStatus::Cancelled
+    );
+
+    // Only the geometric ladder is still locked
+    assert_eq!(client.get_balance(&creator, &sell_token_id).locked, 700 + 40);
+    assert_eq!(client.try_cancel_ladder(&7, &creator), Err(Ok(Error::LadderNotFound)));
 }
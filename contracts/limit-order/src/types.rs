@@ -1,11 +1,66 @@
-use soroban_sdk::{contracttype, Address};
+use soroban_sdk::{contracttype, Address, Vec};
+
+use crate::error::Error;
+use crate::math;
+use crate::oracle::Asset;
 
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum IntentStatus {
     Active,
+    PartiallyFilled,
     Executed,
     Cancelled,
+    Expired,
+}
+
+/// Oracle price source used to evaluate an intent's trigger
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PriceSource {
+    /// Ratio of the assets' last prices (`lastprice`)
+    Spot,
+    /// Ratio of the assets' TWAPs over N records (`twap`)
+    Twap(u32),
+    /// Direct cross price of the pair (`x_last_price`)
+    CrossSpot,
+    /// Cross price TWAP of the pair over N records (`x_twap`)
+    CrossTwap(u32),
+}
+
+/// Direction in which the oracle price must cross the target price
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TriggerDirection {
+    /// Trigger when price >= target (limit / take-profit)
+    Above,
+    /// Trigger when price <= target (stop-loss)
+    Below,
+    /// Trigger when price falls N bps below the highest price observed since
+    /// creation (trailing stop); target_price is the initial high-water mark
+    TrailingStop(u32),
+}
+
+impl TriggerDirection {
+    /// Whether `price` satisfies the trigger at `target_price`
+    pub fn is_met(&self, price: i128, target_price: i128) -> bool {
+        match self {
+            TriggerDirection::Above => price >= target_price,
+            TriggerDirection::Below | TriggerDirection::TrailingStop(_) => price <= target_price,
+        }
+    }
+}
+
+/// How an intent's executor incentive evolves between creation and expiry
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IncentiveCurve {
+    /// Always pays the full incentive
+    Fixed,
+    /// Rises linearly from min_incentive to incentive
+    Linear,
+    /// Rises from min_incentive to incentive in N equal steps
+    Stepwise(u32),
 }
 
 #[contracttype]
@@ -23,19 +78,338 @@ pub struct Intent {
     pub buy_token: Address,
     /// Minimum amount of buy_token to receive
     pub min_buy_amount: i128,
-    /// Target price (scaled by PRICE_SCALE = 1e7)
-    /// price = buy_amount / sell_amount
+    /// Target price (scaled by PRICE_SCALE = 1e7), in whole tokens whatever their decimals
+    /// price = buy_amount / sell_amount, e.g. 0.12 USDC per XLM is 1_200_000
     pub target_price: i128,
     /// Incentive reward for executor (in sell_token)
+    /// Maximum incentive for ramping incentives; this amount is locked
     pub incentive: i128,
+    /// Incentive at creation for ramping incentives (equals incentive when Fixed)
+    pub min_incentive: i128,
+    /// How the incentive ramps from min_incentive to incentive by expiry
+    pub incentive_curve: IncentiveCurve,
     /// Expiration timestamp (ledger timestamp)
     pub expiry: u64,
     /// Current status
     pub status: IntentStatus,
-    /// Executor address (set when executed)
+    /// Executor address (set when executed, last executor for partial fills)
     pub executor: Option<Address>,
-    /// Actual buy amount received (set when executed)
+    /// Actual buy amount received (set when executed, cumulative for partial fills)
     pub actual_buy_amount: Option<i128>,
+    /// Amount of sell_token filled so far
+    pub filled_sell_amount: i128,
+    /// Amount of buy_token delivered to the creator so far
+    pub filled_buy_amount: i128,
+    /// Oracle price source for trigger checks and execution
+    pub price_source: PriceSource,
+    /// Whether the intent triggers above (take-profit) or below (stop-loss) target_price
+    pub trigger_direction: TriggerDirection,
+    /// Ledger timestamp at creation
+    pub created_at: u64,
+    /// Highest oracle price observed since creation (trailing stops only, else 0)
+    pub high_water_mark: i128,
+    /// One-cancels-other group the intent belongs to, if any
+    pub group_id: Option<u64>,
+    /// Sell amount of each tranche (recurring intents only, else 0)
+    pub amount_per_interval: i128,
+    /// Minimum delay between two tranches, in seconds (0 for one-shot intents)
+    pub interval: u64,
+    /// Tranches not yet executed (recurring intents only)
+    pub tranches_remaining: u32,
+    /// Ledger timestamp from which the next tranche can be executed
+    pub next_tranche_at: u64,
+}
+
+/// Parameters of a new intent (see `create_intent`)
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct IntentParams {
+    pub sell_token: Address,
+    pub sell_amount: i128,
+    pub buy_token: Address,
+    pub min_buy_amount: i128,
+    pub target_price: i128,
+    pub incentive: i128,
+    pub expiry: u64,
+    pub price_source: PriceSource,
+    pub trigger_direction: TriggerDirection,
+}
+
+/// One-cancels-other group of intents selling the same token
+/// The first fill of any member cancels all of its siblings
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct IntentGroup {
+    /// Unique group ID
+    pub id: u64,
+    /// User who created the group
+    pub creator: Address,
+    /// Token sold by every member
+    pub sell_token: Address,
+    /// Member intent IDs
+    pub intent_ids: Vec<u64>,
+    /// Shared lock backing the open members (the largest remaining sell amount
+    /// plus incentive); 0 once a member is filled, which then locks its own funds
+    pub locked: i128,
+    /// Member whose first fill cancelled its siblings
+    pub filled_intent: Option<u64>,
+}
+
+/// How a price ladder splits its total amount across its levels
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LadderDistribution {
+    /// Level i (0 = lowest price) sells i + 1 parts: sizes grow linearly with price
+    Linear,
+    /// Each level sells the previous level's size times N bps
+    /// (10_000 for equal sizes, 20_000 to double at every level); the weight of
+    /// the first level is PRICE_SCALE, and no level's weight may round down to 0
+    Geometric(u32),
+}
+
+/// Price ladder: intents selling one amount across evenly spaced price levels
+/// Members are regular intents that fill independently of each other
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct Ladder {
+    /// Unique ladder ID
+    pub id: u64,
+    /// User who created the ladder
+    pub creator: Address,
+    /// Token sold by every level
+    pub sell_token: Address,
+    /// Token bought by every level
+    pub buy_token: Address,
+    /// Member intent IDs, from the lowest to the highest price
+    pub intent_ids: Vec<u64>,
+}
+
+impl Intent {
+    /// Whether the intent can still be filled or cancelled
+    pub fn is_open(&self) -> bool {
+        matches!(self.status, IntentStatus::Active | IntentStatus::PartiallyFilled)
+    }
+
+    /// Whether the intent is open but past its expiry
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.is_open() && now > self.expiry
+    }
+
+    /// Price the oracle must cross for the intent to trigger
+    /// For trailing stops this follows the high-water mark
+    pub fn trigger_price(&self) -> Result<i128, Error> {
+        match self.trigger_direction {
+            TriggerDirection::TrailingStop(trail_bps) => math::mul_div_floor(
+                self.high_water_mark,
+                BPS_DENOMINATOR - trail_bps as i128,
+                BPS_DENOMINATOR,
+            ),
+            _ => Ok(self.target_price),
+        }
+    }
+
+    /// Sell amount not yet filled
+    pub fn remaining_sell_amount(&self) -> i128 {
+        self.sell_amount - self.filled_sell_amount
+    }
+
+    /// Incentive already paid out (pro-rata to the filled sell amount)
+    pub fn paid_incentive(&self) -> Result<i128, Error> {
+        math::mul_div_floor(self.incentive, self.filled_sell_amount, self.sell_amount)
+    }
+
+    /// Incentive not yet paid out
+    pub fn remaining_incentive(&self) -> Result<i128, Error> {
+        math::sub(self.incentive, self.paid_incentive()?)
+    }
+
+    /// Incentive share for filling another `fill_amount` (rounded down)
+    /// The last fill receives any rounding remainder
+    pub fn incentive_for_fill(&self, fill_amount: i128) -> Result<i128, Error> {
+        let filled_after = math::add(self.filled_sell_amount, fill_amount)?;
+        let paid_after = math::mul_div_floor(self.incentive, filled_after, self.sell_amount)?;
+        math::sub(paid_after, self.paid_incentive()?)
+    }
+
+    /// Incentive paid for the whole sell amount at `now` (rounded down)
+    pub fn incentive_at(&self, now: u64) -> Result<i128, Error> {
+        let duration = self.expiry.saturating_sub(self.created_at);
+        let elapsed = now.saturating_sub(self.created_at).min(duration);
+        if duration == 0 {
+            return Ok(self.incentive);
+        }
+
+        let range = math::sub(self.incentive, self.min_incentive)?;
+        let ramp = match self.incentive_curve {
+            IncentiveCurve::Fixed => return Ok(self.incentive),
+            IncentiveCurve::Linear => {
+                math::mul_div_floor(range, elapsed as i128, duration as i128)?
+            }
+            IncentiveCurve::Stepwise(steps) => {
+                let step = math::mul_div_floor(elapsed as i128, steps as i128, duration as i128)?;
+                math::mul_div_floor(range, step, steps as i128)?
+            }
+        };
+        math::add(self.min_incentive, ramp)
+    }
+
+    /// Part of `locked_incentive` (a fill's share of the locked incentive)
+    /// paid to the executor at `now`, rounded down; the rest is refunded to the creator
+    pub fn incentive_payout(&self, locked_incentive: i128, now: u64) -> Result<i128, Error> {
+        if self.incentive == 0 {
+            return Ok(0);
+        }
+        math::mul_div_floor(locked_incentive, self.incentive_at(now)?, self.incentive)
+    }
+
+    /// Minimum buy amount for filling `fill_amount` (pro-rata, rounded up)
+    pub fn min_buy_for_fill(&self, fill_amount: i128) -> Result<i128, Error> {
+        math::mul_div_ceil(self.min_buy_amount, fill_amount, self.sell_amount)
+    }
+
+    /// Whether the intent sells in tranches (DCA)
+    pub fn is_recurring(&self) -> bool {
+        self.interval > 0
+    }
+
+    /// Sell amount that can be filled at `now`: the next tranche once its
+    /// window is open for recurring intents, else whatever remains
+    pub fn fillable_amount(&self, now: u64) -> i128 {
+        if !self.is_recurring() {
+            self.remaining_sell_amount()
+        } else if now < self.next_tranche_at {
+            0
+        } else {
+            self.amount_per_interval
+        }
+    }
+
+    /// Sell amount plus incentive still locked in the creator's balance
+    pub fn remaining_locked(&self) -> Result<i128, Error> {
+        math::add(self.remaining_sell_amount(), self.remaining_incentive()?)
+    }
+}
+
+/// Entry in a per-pair order book, kept sorted by target price
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OrderBookEntry {
+    pub intent_id: u64,
+    pub target_price: i128,
+}
+
+/// Page of a sharded index: where its entries are stored and the sort key of the last one
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexPage {
+    pub page: u32,
+    pub last_key: i128,
+}
+
+/// Directory of a sharded index (the active intents, or a pair's order book)
+/// Pages are listed in sort order; each holds at most INDEX_PAGE_SIZE entries
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct IndexDirectory {
+    /// Number to give the next page created
+    pub next_page: u32,
+    pub pages: Vec<IndexPage>,
+}
+
+/// Part of the contract that can be paused on its own
+/// Withdrawals and cancellations are never paused
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PauseScope {
+    /// Creating intents (single, grouped, recurring or auction)
+    NewIntents,
+    /// Executing intents, directly or through the router
+    Executions,
+    /// Depositing into the vault
+    Deposits,
+}
+
+/// How a batch call handles an item that fails
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BatchMode {
+    /// The first failing item reverts the whole batch
+    AllOrNothing,
+    /// Failing items are skipped and reported; the others go through
+    BestEffort,
+}
+
+/// Side of a fill the protocol fee is taken from
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FeeSide {
+    /// Fee in buy_token, deducted from the amount delivered to the creator
+    Buy,
+    /// Fee in sell_token, deducted from the amount swapped by the executor
+    Sell,
+}
+
+/// Protocol fee charged on execution
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeConfig {
+    /// Fee in basis points (at most MAX_FEE_BPS)
+    pub fee_bps: u32,
+    /// Side of the fill the fee is taken from
+    pub side: FeeSide,
+}
+
+impl FeeConfig {
+    /// Fee on `amount` (rounded down, in favour of users)
+    pub fn fee_on(&self, amount: i128) -> Result<i128, Error> {
+        math::mul_div_floor(amount, self.fee_bps as i128, BPS_DENOMINATOR)
+    }
+
+    /// Fee in sell_token on a fill of `fill_amount`
+    pub fn sell_fee(&self, fill_amount: i128) -> Result<i128, Error> {
+        match self.side {
+            FeeSide::Sell => self.fee_on(fill_amount),
+            FeeSide::Buy => Ok(0),
+        }
+    }
+
+    /// Fee in buy_token on `buy_amount` obtained for a fill
+    pub fn buy_fee(&self, buy_amount: i128) -> Result<i128, Error> {
+        match self.side {
+            FeeSide::Buy => self.fee_on(buy_amount),
+            FeeSide::Sell => Ok(0),
+        }
+    }
+}
+
+/// Supported token, listed by the config manager
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenConfig {
+    /// Minimum sell_amount of an intent selling the token
+    pub min_order_size: i128,
+    /// Maximum sell_amount of an intent selling the token
+    pub max_order_size: i128,
+    /// Asset quoting the token on the oracle, e.g. `Asset::Other("BTC")` for a bridged token
+    pub oracle_asset: Asset,
+    /// Oracle pricing the token, if not the contract's default oracle
+    /// It must quote prices in the same base asset as the default oracle
+    pub oracle: Option<Address>,
+}
+
+/// Ledger TTL policy of the contract's storage
+/// An entry whose TTL drops below the threshold is extended back to `extend_to` ledgers
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TtlConfig {
+    /// TTL (in ledgers) below which intents, balances and indexes are extended
+    pub persistent_threshold: u32,
+    /// TTL (in ledgers) intents, balances and indexes are extended to
+    pub persistent_extend_to: u32,
+    /// TTL (in ledgers) below which the contract instance (config, counters) is extended
+    pub instance_threshold: u32,
+    /// TTL (in ledgers) the contract instance is extended to
+    pub instance_extend_to: u32,
 }
 
 #[contracttype]
@@ -47,5 +421,61 @@ pub struct Balance {
     pub locked: i128,
 }
 
+/// Storage schema version written by this version of the contract
+/// Bump it when the layout of `Intent` or `Balance` changes, and migrate old records in `migrate`
+pub const SCHEMA_VERSION: u32 = 1;
+
 /// Price scale factor (1e7 for 7 decimal precision)
 pub const PRICE_SCALE: i128 = 10_000_000;
+
+/// Basis points denominator (10_000 bps = 100%)
+pub const BPS_DENOMINATOR: i128 = 10_000;
+
+/// Default maximum deviation between the executor's implied price and the
+/// oracle price, in basis points (5%)
+pub const DEFAULT_MAX_DEVIATION_BPS: u32 = 500;
+
+/// Maximum number of tokens in a swap path (3 hops)
+pub const MAX_PATH_LENGTH: u32 = 4;
+
+/// Maximum number of entries returned by paginated queries
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// Maximum number of entries in one page of the active intent index or an order book
+pub const INDEX_PAGE_SIZE: u32 = 64;
+
+/// Maximum number of oracle records for TWAP price sources
+pub const MAX_TWAP_RECORDS: u32 = 20;
+
+/// Maximum number of oracle history records scanned for a trailing stop's
+/// high-water mark (4 hours of 5 minute Reflector periods)
+pub const MAX_HISTORY_RECORDS: u32 = 48;
+
+/// Hard cap on the protocol fee, in basis points (1%)
+pub const MAX_FEE_BPS: u32 = 100;
+
+/// Maximum number of tranches of a recurring intent
+pub const MAX_TRANCHES: u32 = 365;
+
+/// Maximum number of intents in a one-cancels-other group
+pub const MAX_GROUP_SIZE: u32 = 4;
+
+/// Maximum number of items in a batch call
+pub const MAX_BATCH_SIZE: u32 = 20;
+
+/// Default maximum age of an oracle price, in seconds (3 Reflector periods of 5 minutes)
+pub const DEFAULT_MAX_PRICE_AGE: u64 = 900;
+
+/// Ledgers closed per day (5 second ledgers)
+pub const LEDGERS_PER_DAY: u32 = 17_280;
+
+/// Default TTL entries are extended to, in ledgers (300 days)
+pub const DEFAULT_TTL_EXTEND_TO: u32 = 300 * LEDGERS_PER_DAY;
+
+/// Default TTL below which entries are extended, in ledgers (one month before
+/// reaching `DEFAULT_TTL_EXTEND_TO` again)
+pub const DEFAULT_TTL_THRESHOLD: u32 = DEFAULT_TTL_EXTEND_TO - 30 * LEDGERS_PER_DAY;
+
+/// Smallest TTL extension `set_ttl_config` accepts, in ledgers: the network's minimum
+/// persistent entry TTL (7 days), below which an extension would be a no-op
+pub const MIN_TTL_EXTEND_TO: u32 = 7 * LEDGERS_PER_DAY;
@@ -0,0 +1,51 @@
+#![no_std]
+//! Stand-in for the next version of the limit-order contract, used by
+//! `test_upgrade_and_migrate` to upgrade a live contract to a second WASM.
+//!
+//! Version 2 adds a `migrated_at` field to every intent. Its `migrate` entry
+//! point rewrites the records written by version 1 in place, reading them as
+//! raw maps so it does not need the old `Intent` layout.
+//!
+//! Rebuild with `cargo build --target wasm32v1-none --release` from this
+//! directory and copy `limit_order_v2.wasm` to `testdata/`.
+
+use soroban_sdk::{contract, contractimpl, symbol_short, Env, IntoVal, Map, Symbol, Val};
+
+const SCHEMA_VERSION: u32 = 2;
+
+#[contract]
+pub struct LimitOrderV2;
+
+#[contractimpl]
+impl LimitOrderV2 {
+    /// Storage schema version
+    pub fn get_schema_version(e: Env) -> u32 {
+        e.storage().instance().get(&symbol_short!("SCHEMA")).unwrap_or(0)
+    }
+
+    /// Rewrite every intent into the version 2 layout
+    /// Returns the number of intents rewritten
+    pub fn migrate(e: Env) -> u32 {
+        let counter: u64 = e.storage().instance().get(&symbol_short!("COUNTER")).unwrap_or(0);
+        let now: Val = e.ledger().timestamp().into_val(&e);
+
+        let mut migrated = 0;
+        for intent_id in 0..counter {
+            let key = (Symbol::new(&e, "INTENT"), intent_id);
+            let record: Option<Map<Symbol, Val>> = e.storage().persistent().get(&key);
+            if let Some(mut record) = record {
+                record.set(Symbol::new(&e, "migrated_at"), now);
+                e.storage().persistent().set(&key, &record);
+                migrated += 1;
+            }
+        }
+
+        e.storage().instance().set(&symbol_short!("SCHEMA"), &SCHEMA_VERSION);
+        migrated
+    }
+
+    /// Raw intent record
+    pub fn get_intent(e: Env, intent_id: u64) -> Option<Map<Symbol, Val>> {
+        e.storage().persistent().get(&(Symbol::new(&e, "INTENT"), intent_id))
+    }
+}